@@ -9,12 +9,14 @@
 
 */
 use bitwise_games::draw_command::{
-    BLUE, DARK_BLUE, DrawCommand, GREEN, ORANGE, RED, Rectangle, WHITE, YELLOW,
+    BLUE, DARK_BLUE, DrawCommand, GREEN, ORANGE, RED, Rectangle, Style, WHITE, YELLOW,
 };
 
-use bitwise_games::Game;
-use bitwise_games::frame_buffer::FrameBuffer;
-use minifb::Key;
+use bitwise_games::bits;
+use bitwise_games::layout::{BitLayout, Field};
+use bitwise_games::output::{Sound, Waveform};
+use bitwise_games::{Button, Game, Input, bit_layout};
+use std::time::Duration;
 
 const N_BRICK_ROWS: u8 = 5;
 const N_BRICK_COLS: u8 = 8;
@@ -44,79 +46,16 @@ const BALL_UP_RIGHT: u8 = 1;
 const BALL_DOWN_LEFT: u8 = 2;
 const BALL_DOWN_RIGHT: u8 = 3;
 
-struct Breakout {
-    bricks: u64,
-    paddle_pos: u8,
-    ball_pos_x: u8,
-    ball_pos_y: u8,
-    ball_vel: u8,
-}
-
-fn get_bits<T>(value: u64, start: u8, length: u8) -> T
-where
-    T: TryFrom<u64>,
-    T::Error: std::fmt::Debug,
-{
-    // Ensure parameters are within valid range
-    assert!(start < 64, "Start position must be less than 64");
-    assert!(length > 0, "Length must be greater than 0");
-    assert!(start + length <= 64, "Start + length must not exceed 64");
-
-    let mask = if length == 64 {
-        u64::MAX
-    } else {
-        (1u64 << length) - 1
-    };
-
-    let extracted = (value >> start) & mask;
-
-    T::try_from(extracted).unwrap()
-}
-
-fn set_bits<T>(value: u64, data: T, start: u8, length: u8) -> u64
-where
-    u64: From<T>,
-{
-    assert!(start < 64, "Start position must be less than 64");
-    assert!(length > 0, "Length must be greater than 0");
-    assert!(start + length <= 64, "Start + length must not exceed 64");
-
-    let data_u64 = u64::from(data);
-    let mask = if length == 64 {
-        u64::MAX
-    } else {
-        (1u64 << length) - 1
-    };
-
-    // Clear the bits in the target range and set the new bits
-    (value & !(mask << start)) | ((data_u64 & mask) << start)
-}
-
-fn from_u64(state: u64) -> Breakout {
-    let bricks = get_bits(state, 0, N_BRICKS as u8);
-    let paddle_pos = get_bits(state, N_BRICKS as u8, 6);
-    let ball_pos_x = get_bits(state, (N_BRICKS + 6) as u8, 6);
-    let ball_pos_y = get_bits(state, (N_BRICKS + 12) as u8, 6);
-    let ball_vel = get_bits(state, (N_BRICKS + 18) as u8, 2);
-    Breakout {
-        bricks,
-        paddle_pos,
-        ball_pos_x,
-        ball_pos_y,
-        ball_vel,
+bit_layout! {
+    struct Breakout {
+        bricks: u64 = N_BRICKS as u32,
+        paddle_pos: u8 = 6,
+        ball_pos_x: u8 = 6,
+        ball_pos_y: u8 = 6,
+        ball_vel: u8 = 2,
     }
 }
 
-fn to_u64(state: &Breakout) -> u64 {
-    let mut result = 0u64;
-    result = set_bits(result, state.bricks, 0, N_BRICKS as u8);
-    result = set_bits(result, state.paddle_pos, N_BRICKS as u8, 6);
-    result = set_bits(result, state.ball_pos_x, (N_BRICKS + 6) as u8, 6);
-    result = set_bits(result, state.ball_pos_y, (N_BRICKS + 12) as u8, 6);
-    result = set_bits(result, state.ball_vel, (N_BRICKS + 18) as u8, 2);
-    result
-}
-
 fn flip_ball_horizontal(velocity: u8) -> u8 {
     match velocity {
         BALL_UP_LEFT => BALL_UP_RIGHT,
@@ -191,7 +130,7 @@ fn find_brick_collision(ball_x: u8, ball_y: u8, bricks: u64) -> Option<u8> {
             let brick_row = y / BRICK_HEIGHT as u8;
             let brick_index = brick_row * N_BRICK_COLS + brick_col;
 
-            if brick_index < N_BRICKS && (bricks >> brick_index) & 1 == 1 {
+            if brick_index < N_BRICKS && bits::get_flag(bricks, brick_index.into()) {
                 return Some(brick_index);
             }
         }
@@ -224,8 +163,7 @@ fn determine_brick_collision_direction(
     !was_vertically_aligned
 }
 
-fn draw_64x64(state: &Breakout) -> Vec<u32> {
-    let mut fb = FrameBuffer::new(64, 64);
+fn draw(state: &Breakout) -> Vec<DrawCommand> {
     let mut draw_commands = Vec::new();
 
     // Add background
@@ -235,69 +173,45 @@ fn draw_64x64(state: &Breakout) -> Vec<u32> {
         width: BOARD_WIDTH,
         height: BOARD_HEIGHT,
         color: DARK_BLUE,
+        style: Style::Fill,
     }));
 
     // Add bricks
     let brick_colors = [RED, ORANGE, YELLOW, GREEN, BLUE];
-    for i in 0..N_BRICKS {
-        if (state.bricks >> i) & 1 == 1 {
-            let row = u32::from(i / N_BRICK_COLS);
-            let col = u32::from(i % N_BRICK_COLS);
-            draw_commands.push(DrawCommand::Rectangle(Rectangle {
-                x: col * BRICK_WIDTH,
-                y: row * BRICK_HEIGHT,
-                width: BRICK_WIDTH,
-                height: BRICK_HEIGHT,
-                color: brick_colors[row as usize],
-            }));
-        }
+    for i in bits::ones(state.bricks) {
+        let row = i / u32::from(N_BRICK_COLS);
+        let col = i % u32::from(N_BRICK_COLS);
+        draw_commands.push(DrawCommand::Rectangle(Rectangle {
+            x: (col * BRICK_WIDTH) as i32,
+            y: (row * BRICK_HEIGHT) as i32,
+            width: BRICK_WIDTH,
+            height: BRICK_HEIGHT,
+            color: brick_colors[row as usize],
+            style: Style::Fill,
+        }));
     }
 
     // Add paddle
     draw_commands.push(DrawCommand::Rectangle(Rectangle {
-        x: state.paddle_pos as u32,
-        y: PADDLE_Y,
+        x: state.paddle_pos.into(),
+        y: PADDLE_Y as i32,
         width: PADDLE_WIDTH,
         height: PADDLE_HEIGHT,
         color: WHITE,
+        style: Style::Fill,
     }));
 
     // Add ball
     draw_commands.push(DrawCommand::Rectangle(Rectangle {
-        x: state.ball_pos_x as u32,
-        y: state.ball_pos_y as u32,
+        x: state.ball_pos_x.into(),
+        y: state.ball_pos_y.into(),
         width: BALL_SIZE,
         height: BALL_SIZE,
         color: WHITE,
+        style: Style::Fill,
     }));
 
-    // Draw all commands at once
-    fb.draw_list(&draw_commands);
-
-    fb.pixels
-}
-
-fn scale_framebuffer(fb_64x64: &[u32], scale_factor: u32) -> Vec<u32> {
-    let output_size = (BOARD_WIDTH * scale_factor) as usize;
-    let mut scaled_fb = vec![0u32; output_size * output_size];
-
-    for y in 0..BOARD_HEIGHT as usize {
-        for x in 0..BOARD_WIDTH as usize {
-            let pixel = fb_64x64[y * BOARD_WIDTH as usize + x];
-
-            // Scale each pixel to a scale_factor x scale_factor block
-            for dy in 0..scale_factor {
-                for dx in 0..scale_factor {
-                    let scaled_x = x * scale_factor as usize + dx as usize;
-                    let scaled_y = y * scale_factor as usize + dy as usize;
-                    let scaled_index = scaled_y * output_size + scaled_x;
-                    scaled_fb[scaled_index] = pixel;
-                }
-            }
-        }
-    }
-
-    scaled_fb
+    draw_commands
 }
 
 // Collision response functions
@@ -337,7 +251,7 @@ fn handle_brick_collision(
     is_vertical: bool,
 ) {
     // Remove the brick
-    state.bricks &= !(1 << brick_index);
+    state.bricks = bits::set_flag(state.bricks, brick_index.into(), false);
 
     // Bounce the ball
     if is_vertical {
@@ -396,48 +310,42 @@ fn handle_collisions(state: &mut Breakout, dx: i8, dy: i8, old_ball_x: u8, old_b
             brick_index,
         );
         handle_brick_collision(state, old_ball_x, old_ball_y, brick_index, is_vertical);
-        return;
     }
 }
 
-fn update_paddle_position(paddle_pos: u8, input: &[Key]) -> u8 {
+fn update_paddle_position(paddle_pos: u8, input: Input) -> u8 {
     let mut new_paddle_pos = paddle_pos;
-    if input.contains(&Key::Left) {
-        if paddle_pos > 0 {
-            new_paddle_pos -= PADDLE_VELOCITY;
-        }
+    if input.is_pressed(Button::Left) && paddle_pos > 0 {
+        new_paddle_pos -= PADDLE_VELOCITY;
     }
-    if input.contains(&Key::Right) {
-        if paddle_pos < BOARD_WIDTH as u8 - PADDLE_WIDTH as u8 {
-            new_paddle_pos += PADDLE_VELOCITY;
-        }
+    if input.is_pressed(Button::Right) && paddle_pos < BOARD_WIDTH as u8 - PADDLE_WIDTH as u8 {
+        new_paddle_pos += PADDLE_VELOCITY;
     }
     new_paddle_pos
 }
 
 impl Game for Breakout {
     const NAME: &'static str = "Breakout";
-    const WIDTH: usize = 640;
-    const HEIGHT: usize = 640;
+    const WIDTH: usize = BOARD_WIDTH as usize;
+    const HEIGHT: usize = BOARD_HEIGHT as usize;
+    const WINDOW_WIDTH: usize = 640;
+    const WINDOW_HEIGHT: usize = 640;
     const FPS: usize = 30;
+    const LAYOUT: &'static [Field] = <Breakout as BitLayout>::FIELDS;
 
-    fn new(_args: Vec<String>) -> (u64, Vec<u32>) {
+    fn new(_args: Vec<String>) -> u64 {
         let state = Breakout {
-            bricks: (1 << N_BRICKS) - 1,
+            bricks: bits::mask(N_BRICKS.into()),
             paddle_pos: ((BOARD_WIDTH - PADDLE_WIDTH) / 2) as u8,
             ball_pos_x: ((BOARD_WIDTH - BALL_SIZE) / 2) as u8,
             ball_pos_y: 57, // just above paddle
             ball_vel: BALL_UP_RIGHT,
         };
-        let state_u64 = to_u64(&state);
-        let fb_64x64 = draw_64x64(&state);
-        let scale_factor = (Breakout::WIDTH / BOARD_WIDTH as usize) as u32;
-        let fb = scale_framebuffer(&fb_64x64, scale_factor);
-        (state_u64, fb)
+        state.to_u64()
     }
 
-    fn update(state_u64: u64, input: &[Key]) -> (u64, Vec<u32>) {
-        let mut state = from_u64(state_u64);
+    fn step(state_u64: u64, input: Input) -> u64 {
+        let mut state = Breakout::from_u64(state_u64);
 
         state.paddle_pos = update_paddle_position(state.paddle_pos, input);
 
@@ -458,14 +366,130 @@ impl Game for Breakout {
 
         handle_collisions(&mut state, dx, dy, old_ball_x, old_ball_y);
 
-        let new_state_u64 = to_u64(&state);
-        let fb_64x64 = draw_64x64(&state);
-        let scale_factor = (Breakout::WIDTH / BOARD_WIDTH as usize) as u32;
-        let fb = scale_framebuffer(&fb_64x64, scale_factor);
-        (new_state_u64, fb)
+        state.to_u64()
+    }
+
+    fn render(state: u64) -> Vec<DrawCommand> {
+        draw(&Breakout::from_u64(state))
+    }
+
+    fn sounds(previous: u64, next: u64) -> Vec<Sound> {
+        // A blip for every brick that disappeared, higher for the rows further up
+        let hit = Breakout::from_u64(previous).bricks & !Breakout::from_u64(next).bricks;
+        bits::ones(hit)
+            .map(|brick| Sound {
+                waveform: Waveform::Square,
+                pitch: 40 - 3 * (brick / u32::from(N_BRICK_COLS)) as u8,
+                duration: Duration::from_millis(80),
+                volume: 0.5,
+            })
+            .collect()
     }
 }
 
 fn main() {
     bitwise_games::run_game::<Breakout>();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitwise_games::snapshot::Snapshot;
+
+    const SNAPSHOTS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots/breakout");
+
+    fn snapshot(name: &str, state: Breakout) -> Snapshot<Breakout> {
+        Snapshot::new(SNAPSHOTS, name, state.to_u64())
+    }
+
+    fn start() -> Breakout {
+        Breakout::from_u64(Breakout::new(Vec::new()))
+    }
+
+    fn with_ball(x: u8, y: u8, velocity: u8) -> Breakout {
+        Breakout {
+            ball_pos_x: x,
+            ball_pos_y: y,
+            ball_vel: velocity,
+            ..start()
+        }
+    }
+
+    fn end_state(snapshot: &Snapshot<Breakout>) -> Breakout {
+        Breakout::from_u64(snapshot.state())
+    }
+
+    #[test]
+    fn renders_the_start() {
+        snapshot("start", start()).capture().assert();
+    }
+
+    #[test]
+    fn renders_missing_bricks() {
+        let state = Breakout {
+            bricks: 0x00aa_55aa_55aa & bits::mask(N_BRICKS.into()),
+            ..start()
+        };
+        snapshot("missing_bricks", state).capture().assert();
+    }
+
+    #[test]
+    fn paddle_stops_at_the_walls() {
+        let mut snapshot = snapshot("paddle_limits", start());
+        snapshot
+            .capture()
+            .step([Input::from(Button::Left); 20])
+            .capture();
+        assert_eq!(end_state(&snapshot).paddle_pos, 0);
+        snapshot.step([Input::from(Button::Right); 40]).capture();
+        assert_eq!(
+            u32::from(end_state(&snapshot).paddle_pos),
+            BOARD_WIDTH - PADDLE_WIDTH
+        );
+        snapshot.assert();
+    }
+
+    #[test]
+    fn ball_bounces_off_the_wall() {
+        let mut snapshot = snapshot("wall_bounce", with_ball(60, 30, BALL_UP_RIGHT));
+        snapshot.capture().step([Input::NONE; 4]).capture();
+        let state = end_state(&snapshot);
+        assert_eq!(state.ball_vel, BALL_UP_LEFT);
+        assert_eq!((state.ball_pos_x, state.ball_pos_y), (59, 26));
+        snapshot.assert();
+    }
+
+    #[test]
+    fn ball_bounces_off_the_paddle() {
+        let mut snapshot = snapshot("paddle_bounce", with_ball(30, 58, BALL_DOWN_RIGHT));
+        snapshot.capture().step([Input::NONE; 2]).capture();
+        let state = end_state(&snapshot);
+        assert_eq!(state.ball_vel, BALL_UP_RIGHT);
+        snapshot.step([Input::NONE; 3]).capture().assert();
+    }
+
+    #[test]
+    fn ball_breaks_a_brick() {
+        let mut snapshot = snapshot("brick_hit", with_ball(20, 21, BALL_UP_LEFT));
+        snapshot.capture().step([Input::NONE; 2]).capture();
+        let state = end_state(&snapshot);
+        assert!(!bits::get_flag(state.bricks, 34));
+        assert_eq!(state.bricks.count_ones(), u32::from(N_BRICKS) - 1);
+        assert_eq!(state.ball_vel, BALL_DOWN_LEFT);
+        snapshot.step([Input::NONE; 3]).capture().assert();
+    }
+
+    #[test]
+    fn missed_ball_is_served_again() {
+        let state = Breakout {
+            paddle_pos: 40,
+            ..with_ball(10, 60, BALL_DOWN_RIGHT)
+        };
+        let mut snapshot = snapshot("ball_lost", state);
+        snapshot.capture().step([Input::NONE; 2]).capture();
+        let state = end_state(&snapshot);
+        assert_eq!((state.ball_pos_x, state.ball_pos_y), (31, 60));
+        assert_eq!(state.ball_vel, BALL_UP_RIGHT);
+        snapshot.assert();
+    }
+}
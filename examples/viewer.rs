@@ -0,0 +1,67 @@
+//! Shows a stream of draw command frames, such as one written by a game's
+//! `--dump` flag.
+//!
+//! ```sh
+//! mkfifo /tmp/frames
+//! cargo run --example viewer -- 64 64 /tmp/frames &
+//! cargo run --example breakout -- --dump /tmp/frames
+//! ```
+//!
+//! Without a path the frames are read from stdin.
+
+use bitwise_games::command_list;
+use bitwise_games::frame_buffer::FrameBuffer;
+use minifb::{Key, Scale, Window, WindowOptions};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::sync::mpsc;
+use std::thread;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let size = |i: usize| args.get(i).map_or(64, |arg| arg.parse().expect("size"));
+    let (width, height) = (size(0), size(1));
+    let input: Box<dyn Read + Send> = match args.get(2) {
+        Some(path) => Box::new(File::open(path).unwrap_or_else(|e| panic!("{e}"))),
+        None => Box::new(io::stdin()),
+    };
+
+    // Reads block, so they happen on their own thread and the window keeps
+    // responding between frames
+    let (frames, received) = mpsc::channel();
+    thread::spawn(move || {
+        let mut input = BufReader::new(input);
+        loop {
+            match command_list::read_frame(&mut input) {
+                Ok(Some(commands)) => {
+                    if frames.send(commands).is_err() {
+                        return;
+                    }
+                }
+                Ok(None) => return,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return;
+                }
+            }
+        }
+    });
+
+    let window_options = WindowOptions {
+        scale: Scale::X8,
+        ..WindowOptions::default()
+    };
+    let mut window = Window::new("Viewer", width, height, window_options).unwrap();
+    window.set_target_fps(60);
+    let mut fb = FrameBuffer::new(width as u32, height as u32);
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        if let Some(commands) = received.try_iter().last() {
+            fb = FrameBuffer::new(width as u32, height as u32);
+            fb.draw_list(&commands);
+        }
+        window
+            .update_with_buffer(&fb.pixels, width, height)
+            .unwrap();
+    }
+}
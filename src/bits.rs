@@ -0,0 +1,114 @@
+//! Helpers for reading and writing bit fields of a packed `u64` state.
+//!
+//! Fields are addressed by `offset` (the lowest bit) and `width`. Bits beyond
+//! the 64th are treated as always zero instead of panicking, so a field that
+//! runs off the end is simply truncated.
+
+/// A mask with the low `width` bits set.
+pub const fn mask(width: u32) -> u64 {
+    if width >= 64 {
+        u64::MAX
+    } else {
+        (1 << width) - 1
+    }
+}
+
+const fn shl(value: u64, offset: u32) -> u64 {
+    match value.checked_shl(offset) {
+        Some(shifted) => shifted,
+        None => 0,
+    }
+}
+
+const fn shr(value: u64, offset: u32) -> u64 {
+    match value.checked_shr(offset) {
+        Some(shifted) => shifted,
+        None => 0,
+    }
+}
+
+/// Extracts the `width` bit field starting at `offset`.
+pub const fn get(value: u64, offset: u32, width: u32) -> u64 {
+    shr(value, offset) & mask(width)
+}
+
+/// Replaces the `width` bit field starting at `offset` with the low bits of `field`.
+pub const fn set(value: u64, offset: u32, width: u32, field: u64) -> u64 {
+    let mask = shl(mask(width), offset);
+    (value & !mask) | (shl(field, offset) & mask)
+}
+
+/// Interprets the low `width` bits of `bits` as a two's-complement number.
+pub const fn sign_extend(bits: u64, width: u32) -> i64 {
+    if width == 0 {
+        return 0;
+    }
+    if width >= 64 {
+        return bits as i64;
+    }
+    let unused = 64 - width;
+    ((bits << unused) as i64) >> unused
+}
+
+/// Extracts a two's-complement field, e.g. a velocity that can be negative.
+pub const fn get_signed(value: u64, offset: u32, width: u32) -> i64 {
+    sign_extend(get(value, offset, width), width)
+}
+
+/// Stores `field` as a `width` bit two's-complement number.
+pub const fn set_signed(value: u64, offset: u32, width: u32, field: i64) -> u64 {
+    set(value, offset, width, field as u64)
+}
+
+pub const fn get_flag(value: u64, bit: u32) -> bool {
+    get(value, bit, 1) == 1
+}
+
+pub const fn set_flag(value: u64, bit: u32, on: bool) -> u64 {
+    set(value, bit, 1, on as u64)
+}
+
+pub const fn toggle_flag(value: u64, bit: u32) -> u64 {
+    value ^ shl(1, bit)
+}
+
+/// Number of set bits in the `width` bit field starting at `offset`.
+pub const fn count_ones(value: u64, offset: u32, width: u32) -> u32 {
+    get(value, offset, width).count_ones()
+}
+
+/// Index of the lowest set bit.
+pub const fn first_set(value: u64) -> Option<u32> {
+    if value == 0 {
+        None
+    } else {
+        Some(value.trailing_zeros())
+    }
+}
+
+/// Index of the highest set bit.
+pub const fn last_set(value: u64) -> Option<u32> {
+    if value == 0 {
+        None
+    } else {
+        Some(63 - value.leading_zeros())
+    }
+}
+
+/// Iterates over the `width` bits of a field as flags, lowest bit first.
+///
+/// Handy for bit arrays such as a grid of bricks.
+pub fn iter(value: u64, offset: u32, width: u32) -> impl Iterator<Item = bool> {
+    let field = get(value, offset, width);
+    (0..width.min(64)).map(move |bit| get_flag(field, bit))
+}
+
+/// Iterates over the indices of the set bits, lowest first.
+pub fn ones(value: u64) -> impl Iterator<Item = u32> {
+    let mut remaining = value;
+    std::iter::from_fn(move || {
+        let bit = first_set(remaining)?;
+        remaining &= remaining - 1;
+        Some(bit)
+    })
+}
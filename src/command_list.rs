@@ -0,0 +1,844 @@
+//! Binary and text encodings of draw command lists.
+//!
+//! Both forms carry a format version so that logs and streams written today
+//! stay readable. The binary form is compact enough to stream every frame to
+//! another process; the text form puts one command per line for logs and
+//! diffs in tests:
+//!
+//! ```text
+//! bitwise-draw 1
+//! rect 0 0 64 64 #1d2b53 fill
+//! line 0 63 63 0 #ff004d
+//! circle 32 32 6 #ffec2780 stroke
+//! text 1 1 1 #fff1e8 "SCORE 10"
+//! ```
+
+use crate::draw_command::{
+    BlendMode, Circle, Clip, Color, DrawCommand, Line, Polygon, Rectangle, Sprite, Style, Text,
+    Triangle,
+};
+use std::fmt::{self, Write as _};
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"BWDC";
+const TEXT_HEADER: &str = "bitwise-draw";
+const VERSION: u8 = 1;
+
+/// The largest frame [`read_frame`] accepts. The length prefix comes from the
+/// stream, so it is checked before anything is allocated for it.
+pub const MAX_FRAME_BYTES: usize = 16 << 20;
+
+const BLEND_MODE: u8 = 0;
+const CLIP: u8 = 1;
+const RECTANGLE: u8 = 2;
+const LINE: u8 = 3;
+const CIRCLE: u8 = 4;
+const TRIANGLE: u8 = 5;
+const POLYGON: u8 = 6;
+const TEXT: u8 = 7;
+const SPRITE: u8 = 8;
+
+#[derive(Debug)]
+pub enum CommandListError {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+    UnknownCommand(u8),
+    /// A field of a known command holds a value it can't have, e.g. a number
+    /// too wide for its type or text that isn't UTF-8. Names the field.
+    InvalidField(&'static str),
+    /// A stream's frame is longer than [`MAX_FRAME_BYTES`].
+    FrameTooLarge(u32),
+    /// A line of the text form that could not be parsed, counting from 1.
+    Syntax {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for CommandListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandListError::Io(err) => write!(f, "command list io error: {err}"),
+            CommandListError::BadMagic => write!(f, "not a command list"),
+            CommandListError::UnsupportedVersion(version) => {
+                write!(f, "unsupported command list version {version}")
+            }
+            CommandListError::Truncated => write!(f, "command list is truncated"),
+            CommandListError::UnknownCommand(tag) => write!(f, "unknown draw command {tag}"),
+            CommandListError::InvalidField(field) => write!(f, "invalid {field} in command list"),
+            CommandListError::FrameTooLarge(len) => {
+                write!(f, "frame of {len} bytes is over {MAX_FRAME_BYTES}")
+            }
+            CommandListError::Syntax { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for CommandListError {}
+
+impl From<io::Error> for CommandListError {
+    fn from(err: io::Error) -> Self {
+        CommandListError::Io(err)
+    }
+}
+
+/// Encodes a command list as a header followed by one tagged record per command.
+///
+/// Layout: magic, version byte, command count, then each command as a tag byte
+/// and its fields. Unsigned numbers are varints, signed ones zigzag varints,
+/// colours four RGBA bytes and strings a length followed by UTF-8.
+pub fn encode(commands: &[DrawCommand]) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(VERSION);
+    write_varint(&mut bytes, commands.len() as u64);
+    for command in commands {
+        encode_command(&mut bytes, command);
+    }
+    bytes
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Vec<DrawCommand>, CommandListError> {
+    let (magic, rest) = bytes
+        .split_at_checked(4)
+        .ok_or(CommandListError::Truncated)?;
+    if magic != MAGIC {
+        return Err(CommandListError::BadMagic);
+    }
+    let mut reader = Reader { bytes: rest };
+    let version = reader.byte()?;
+    if version != VERSION {
+        return Err(CommandListError::UnsupportedVersion(version));
+    }
+    let count = reader.varint()?;
+    // Every command takes at least a byte, which bounds what a corrupt count can allocate
+    let mut commands = Vec::with_capacity(count.min(reader.bytes.len() as u64) as usize);
+    for _ in 0..count {
+        commands.push(reader.command()?);
+    }
+    Ok(commands)
+}
+
+/// Writes one frame to a stream, prefixed with its length so that
+/// [`read_frame`] can find where it ends. Fails for frames over
+/// [`MAX_FRAME_BYTES`], which could not be read back.
+pub fn write_frame(writer: &mut impl Write, commands: &[DrawCommand]) -> io::Result<()> {
+    let bytes = encode(commands);
+    if bytes.len() > MAX_FRAME_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("frame of {} bytes is over {MAX_FRAME_BYTES}", bytes.len()),
+        ));
+    }
+    writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(&bytes)?;
+    writer.flush()
+}
+
+/// Reads the next frame written by [`write_frame`], or `None` at the end of
+/// the stream.
+pub fn read_frame(reader: &mut impl Read) -> Result<Option<Vec<DrawCommand>>, CommandListError> {
+    let mut prefix = Vec::with_capacity(4);
+    reader.take(4).read_to_end(&mut prefix)?;
+    let len = match prefix.as_slice() {
+        [] => return Ok(None),
+        &[a, b, c, d] => u32::from_le_bytes([a, b, c, d]),
+        _ => return Err(CommandListError::Truncated),
+    };
+    if len as usize > MAX_FRAME_BYTES {
+        return Err(CommandListError::FrameTooLarge(len));
+    }
+    // Grows with the data actually read rather than trusting the prefix
+    let mut bytes = Vec::new();
+    reader.take(len.into()).read_to_end(&mut bytes)?;
+    if bytes.len() < len as usize {
+        return Err(CommandListError::Truncated);
+    }
+    decode(&bytes).map(Some)
+}
+
+/// The text form: a header line, then one command per line.
+pub fn to_text(commands: &[DrawCommand]) -> String {
+    let mut text = format!("{TEXT_HEADER} {VERSION}\n");
+    for command in commands {
+        let _ = match command {
+            DrawCommand::BlendMode(mode) => {
+                let mode = match mode {
+                    BlendMode::Alpha => "alpha",
+                    BlendMode::Add => "add",
+                    BlendMode::Multiply => "multiply",
+                };
+                writeln!(text, "blend {mode}")
+            }
+            DrawCommand::Clip(None) => writeln!(text, "clip none"),
+            DrawCommand::Clip(Some(clip)) => writeln!(
+                text,
+                "clip {} {} {} {}",
+                clip.x, clip.y, clip.width, clip.height
+            ),
+            DrawCommand::Rectangle(rect) => writeln!(
+                text,
+                "rect {} {} {} {} {} {}",
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+                color_text(&rect.color),
+                style_text(rect.style)
+            ),
+            DrawCommand::Line(line) => writeln!(
+                text,
+                "line {} {} {} {} {}",
+                line.x1,
+                line.y1,
+                line.x2,
+                line.y2,
+                color_text(&line.color)
+            ),
+            DrawCommand::Circle(circle) => writeln!(
+                text,
+                "circle {} {} {} {} {}",
+                circle.x,
+                circle.y,
+                circle.radius,
+                color_text(&circle.color),
+                style_text(circle.style)
+            ),
+            DrawCommand::Triangle(triangle) => writeln!(
+                text,
+                "triangle {} {} {} {} {} {} {} {}",
+                triangle.x1,
+                triangle.y1,
+                triangle.x2,
+                triangle.y2,
+                triangle.x3,
+                triangle.y3,
+                color_text(&triangle.color),
+                style_text(triangle.style)
+            ),
+            DrawCommand::Polygon(polygon) => {
+                let points: Vec<String> = polygon
+                    .points
+                    .iter()
+                    .map(|(x, y)| format!("{x},{y}"))
+                    .collect();
+                writeln!(
+                    text,
+                    "polygon {} {} {}",
+                    color_text(&polygon.color),
+                    style_text(polygon.style),
+                    if points.is_empty() {
+                        "-".to_string()
+                    } else {
+                        points.join(" ")
+                    }
+                )
+            }
+            DrawCommand::Text(command) => writeln!(
+                text,
+                "text {} {} {} {} {:?}",
+                command.x,
+                command.y,
+                command.scale,
+                color_text(&command.color),
+                command.text
+            ),
+            DrawCommand::Sprite(sprite) => {
+                let flip = match (sprite.flip_x, sprite.flip_y) {
+                    (false, false) => "-",
+                    (true, false) => "x",
+                    (false, true) => "y",
+                    (true, true) => "xy",
+                };
+                let data: Vec<String> =
+                    sprite.data.iter().map(|word| format!("{word:x}")).collect();
+                let colors: Vec<String> = sprite.colors.iter().map(color_text).collect();
+                writeln!(
+                    text,
+                    "sprite {} {} {} {} {} {} {flip} {} {}",
+                    sprite.x,
+                    sprite.y,
+                    sprite.width,
+                    sprite.height,
+                    sprite.depth,
+                    sprite.scale,
+                    list_text(&data),
+                    list_text(&colors)
+                )
+            }
+        };
+    }
+    text
+}
+
+/// Parses the text form. Blank lines are skipped.
+pub fn from_text(text: &str) -> Result<Vec<DrawCommand>, CommandListError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty());
+
+    let Some((_, header)) = lines.next() else {
+        return Err(CommandListError::Truncated);
+    };
+    let version = match header.split_once(' ') {
+        Some((TEXT_HEADER, version)) => version,
+        _ => return Err(CommandListError::BadMagic),
+    };
+    match version.parse() {
+        Ok(VERSION) => {}
+        Ok(version) => return Err(CommandListError::UnsupportedVersion(version)),
+        Err(_) => return Err(CommandListError::BadMagic),
+    }
+
+    lines
+        .map(|(number, line)| {
+            parse_command(line).map_err(|message| CommandListError::Syntax {
+                line: number,
+                message,
+            })
+        })
+        .collect()
+}
+
+fn encode_command(bytes: &mut Vec<u8>, command: &DrawCommand) {
+    match command {
+        DrawCommand::BlendMode(mode) => {
+            bytes.push(BLEND_MODE);
+            bytes.push(match mode {
+                BlendMode::Alpha => 0,
+                BlendMode::Add => 1,
+                BlendMode::Multiply => 2,
+            });
+        }
+        DrawCommand::Clip(clip) => {
+            bytes.push(CLIP);
+            match clip {
+                None => bytes.push(0),
+                Some(clip) => {
+                    bytes.push(1);
+                    write_signed(bytes, clip.x);
+                    write_signed(bytes, clip.y);
+                    write_varint(bytes, clip.width.into());
+                    write_varint(bytes, clip.height.into());
+                }
+            }
+        }
+        DrawCommand::Rectangle(rect) => {
+            bytes.push(RECTANGLE);
+            write_signed(bytes, rect.x);
+            write_signed(bytes, rect.y);
+            write_varint(bytes, rect.width.into());
+            write_varint(bytes, rect.height.into());
+            write_color(bytes, &rect.color);
+            write_style(bytes, rect.style);
+        }
+        DrawCommand::Line(line) => {
+            bytes.push(LINE);
+            for v in [line.x1, line.y1, line.x2, line.y2] {
+                write_signed(bytes, v);
+            }
+            write_color(bytes, &line.color);
+        }
+        DrawCommand::Circle(circle) => {
+            bytes.push(CIRCLE);
+            write_signed(bytes, circle.x);
+            write_signed(bytes, circle.y);
+            write_varint(bytes, circle.radius.into());
+            write_color(bytes, &circle.color);
+            write_style(bytes, circle.style);
+        }
+        DrawCommand::Triangle(triangle) => {
+            bytes.push(TRIANGLE);
+            let Triangle {
+                x1,
+                y1,
+                x2,
+                y2,
+                x3,
+                y3,
+                ..
+            } = *triangle;
+            for v in [x1, y1, x2, y2, x3, y3] {
+                write_signed(bytes, v);
+            }
+            write_color(bytes, &triangle.color);
+            write_style(bytes, triangle.style);
+        }
+        DrawCommand::Polygon(polygon) => {
+            bytes.push(POLYGON);
+            write_varint(bytes, polygon.points.len() as u64);
+            for &(x, y) in &polygon.points {
+                write_signed(bytes, x);
+                write_signed(bytes, y);
+            }
+            write_color(bytes, &polygon.color);
+            write_style(bytes, polygon.style);
+        }
+        DrawCommand::Text(text) => {
+            bytes.push(TEXT);
+            write_signed(bytes, text.x);
+            write_signed(bytes, text.y);
+            write_varint(bytes, text.text.len() as u64);
+            bytes.extend_from_slice(text.text.as_bytes());
+            write_varint(bytes, text.scale.into());
+            write_color(bytes, &text.color);
+        }
+        DrawCommand::Sprite(sprite) => {
+            bytes.push(SPRITE);
+            write_signed(bytes, sprite.x);
+            write_signed(bytes, sprite.y);
+            write_varint(bytes, sprite.width.into());
+            write_varint(bytes, sprite.height.into());
+            write_varint(bytes, sprite.depth.into());
+            write_varint(bytes, sprite.data.len() as u64);
+            for word in &sprite.data {
+                bytes.extend_from_slice(&word.to_le_bytes());
+            }
+            write_varint(bytes, sprite.colors.len() as u64);
+            for color in &sprite.colors {
+                write_color(bytes, color);
+            }
+            bytes.push(u8::from(sprite.flip_x) | (u8::from(sprite.flip_y) << 1));
+            write_varint(bytes, sprite.scale.into());
+        }
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Zigzag encoding keeps small negative numbers small.
+fn write_signed(bytes: &mut Vec<u8>, value: i32) {
+    write_varint(bytes, u64::from(((value << 1) ^ (value >> 31)) as u32));
+}
+
+fn write_color(bytes: &mut Vec<u8>, color: &Color) {
+    bytes.extend_from_slice(&[color.r, color.g, color.b, color.a]);
+}
+
+fn write_style(bytes: &mut Vec<u8>, style: Style) {
+    bytes.push(match style {
+        Style::Fill => 0,
+        Style::Stroke => 1,
+    });
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn command(&mut self) -> Result<DrawCommand, CommandListError> {
+        let tag = self.byte()?;
+        Ok(match tag {
+            BLEND_MODE => DrawCommand::BlendMode(match self.byte()? {
+                0 => BlendMode::Alpha,
+                1 => BlendMode::Add,
+                2 => BlendMode::Multiply,
+                _ => return Err(CommandListError::InvalidField("blend mode")),
+            }),
+            CLIP => DrawCommand::Clip(match self.byte()? {
+                0 => None,
+                1 => Some(Clip {
+                    x: self.signed()?,
+                    y: self.signed()?,
+                    width: self.unsigned()?,
+                    height: self.unsigned()?,
+                }),
+                _ => return Err(CommandListError::InvalidField("clip")),
+            }),
+            RECTANGLE => DrawCommand::Rectangle(Rectangle {
+                x: self.signed()?,
+                y: self.signed()?,
+                width: self.unsigned()?,
+                height: self.unsigned()?,
+                color: self.color()?,
+                style: self.style()?,
+            }),
+            LINE => DrawCommand::Line(Line {
+                x1: self.signed()?,
+                y1: self.signed()?,
+                x2: self.signed()?,
+                y2: self.signed()?,
+                color: self.color()?,
+            }),
+            CIRCLE => DrawCommand::Circle(Circle {
+                x: self.signed()?,
+                y: self.signed()?,
+                radius: self.unsigned()?,
+                color: self.color()?,
+                style: self.style()?,
+            }),
+            TRIANGLE => DrawCommand::Triangle(Triangle {
+                x1: self.signed()?,
+                y1: self.signed()?,
+                x2: self.signed()?,
+                y2: self.signed()?,
+                x3: self.signed()?,
+                y3: self.signed()?,
+                color: self.color()?,
+                style: self.style()?,
+            }),
+            POLYGON => {
+                let count = self.length()?;
+                let mut points = Vec::with_capacity(count);
+                for _ in 0..count {
+                    points.push((self.signed()?, self.signed()?));
+                }
+                DrawCommand::Polygon(Polygon {
+                    points,
+                    color: self.color()?,
+                    style: self.style()?,
+                })
+            }
+            TEXT => {
+                let (x, y) = (self.signed()?, self.signed()?);
+                let len = self.length()?;
+                let text = String::from_utf8(self.take(len)?.to_vec())
+                    .map_err(|_| CommandListError::InvalidField("text"))?;
+                DrawCommand::Text(Text {
+                    x,
+                    y,
+                    text,
+                    scale: self.unsigned()?,
+                    color: self.color()?,
+                })
+            }
+            SPRITE => {
+                let (x, y) = (self.signed()?, self.signed()?);
+                let (width, height, depth) = (self.unsigned()?, self.unsigned()?, self.unsigned()?);
+                let words = self.length()?;
+                let mut data = Vec::with_capacity(words);
+                for _ in 0..words {
+                    let word = self.take(8)?;
+                    data.push(u64::from_le_bytes(word.try_into().unwrap_or_default()));
+                }
+                let count = self.length()?;
+                let mut colors = Vec::with_capacity(count);
+                for _ in 0..count {
+                    colors.push(self.color()?);
+                }
+                let flip = self.byte()?;
+                if flip > 3 {
+                    return Err(CommandListError::InvalidField("sprite flip"));
+                }
+                DrawCommand::Sprite(Sprite {
+                    x,
+                    y,
+                    width,
+                    height,
+                    depth,
+                    data,
+                    colors,
+                    flip_x: flip & 1 != 0,
+                    flip_y: flip & 2 != 0,
+                    scale: self.unsigned()?,
+                })
+            }
+            _ => return Err(CommandListError::UnknownCommand(tag)),
+        })
+    }
+
+    fn take(&mut self, len: usize) -> Result<&[u8], CommandListError> {
+        let (head, rest) = self
+            .bytes
+            .split_at_checked(len)
+            .ok_or(CommandListError::Truncated)?;
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn byte(&mut self) -> Result<u8, CommandListError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64, CommandListError> {
+        let mut value = 0u64;
+        for i in 0..10 {
+            let byte = self.byte()?;
+            // The tenth byte only has room for the top bit
+            if i == 9 && byte > 1 {
+                return Err(CommandListError::InvalidField("number"));
+            }
+            value |= u64::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(CommandListError::Truncated)
+    }
+
+    fn unsigned(&mut self) -> Result<u32, CommandListError> {
+        u32::try_from(self.varint()?).map_err(|_| CommandListError::InvalidField("number"))
+    }
+
+    fn signed(&mut self) -> Result<i32, CommandListError> {
+        let zigzag = self.unsigned()?;
+        Ok((zigzag >> 1) as i32 ^ -((zigzag & 1) as i32))
+    }
+
+    /// A count of items that each take at least a byte, so it can't be
+    /// larger than what is left.
+    fn length(&mut self) -> Result<usize, CommandListError> {
+        let len = self.varint()?;
+        if len > self.bytes.len() as u64 {
+            return Err(CommandListError::Truncated);
+        }
+        Ok(len as usize)
+    }
+
+    fn color(&mut self) -> Result<Color, CommandListError> {
+        let [r, g, b, a] = self.take(4)? else {
+            return Err(CommandListError::Truncated);
+        };
+        Ok(Color::from_rgba(*r, *g, *b, *a))
+    }
+
+    fn style(&mut self) -> Result<Style, CommandListError> {
+        match self.byte()? {
+            0 => Ok(Style::Fill),
+            1 => Ok(Style::Stroke),
+            _ => Err(CommandListError::InvalidField("style")),
+        }
+    }
+}
+
+fn color_text(color: &Color) -> String {
+    let rgb = format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b);
+    if color.a == 255 {
+        rgb
+    } else {
+        format!("{rgb}{:02x}", color.a)
+    }
+}
+
+fn style_text(style: Style) -> &'static str {
+    match style {
+        Style::Fill => "fill",
+        Style::Stroke => "stroke",
+    }
+}
+
+/// Comma separated, or `-` for an empty list.
+fn list_text(items: &[String]) -> String {
+    if items.is_empty() {
+        "-".to_string()
+    } else {
+        items.join(",")
+    }
+}
+
+fn parse_command(line: &str) -> Result<DrawCommand, String> {
+    let (name, rest) = line.split_once(' ').unwrap_or((line, ""));
+    if name == "text" {
+        return parse_text(rest);
+    }
+    let mut fields = Fields(rest.split_whitespace());
+    let command = match name {
+        "blend" => DrawCommand::BlendMode(match fields.next()? {
+            "alpha" => BlendMode::Alpha,
+            "add" => BlendMode::Add,
+            "multiply" => BlendMode::Multiply,
+            mode => return Err(format!("unknown blend mode {mode}")),
+        }),
+        "clip" if rest.trim() == "none" => {
+            fields.next()?;
+            DrawCommand::Clip(None)
+        }
+        "clip" => DrawCommand::Clip(Some(Clip {
+            x: fields.number()?,
+            y: fields.number()?,
+            width: fields.number()?,
+            height: fields.number()?,
+        })),
+        "rect" => DrawCommand::Rectangle(Rectangle {
+            x: fields.number()?,
+            y: fields.number()?,
+            width: fields.number()?,
+            height: fields.number()?,
+            color: fields.color()?,
+            style: fields.style()?,
+        }),
+        "line" => DrawCommand::Line(Line {
+            x1: fields.number()?,
+            y1: fields.number()?,
+            x2: fields.number()?,
+            y2: fields.number()?,
+            color: fields.color()?,
+        }),
+        "circle" => DrawCommand::Circle(Circle {
+            x: fields.number()?,
+            y: fields.number()?,
+            radius: fields.number()?,
+            color: fields.color()?,
+            style: fields.style()?,
+        }),
+        "triangle" => DrawCommand::Triangle(Triangle {
+            x1: fields.number()?,
+            y1: fields.number()?,
+            x2: fields.number()?,
+            y2: fields.number()?,
+            x3: fields.number()?,
+            y3: fields.number()?,
+            color: fields.color()?,
+            style: fields.style()?,
+        }),
+        "polygon" => {
+            let color = fields.color()?;
+            let style = fields.style()?;
+            // The points take up the rest of the line
+            let points = match fields.0.by_ref().collect::<Vec<_>>().as_slice() {
+                ["-"] => Vec::new(),
+                points => points
+                    .iter()
+                    .map(|point| {
+                        let (x, y) = point.split_once(',').ok_or(point)?;
+                        Ok((x.parse().map_err(|_| point)?, y.parse().map_err(|_| point)?))
+                    })
+                    .collect::<Result<_, &&str>>()
+                    .map_err(|point| format!("bad point {point}"))?,
+            };
+            DrawCommand::Polygon(Polygon {
+                points,
+                color,
+                style,
+            })
+        }
+        "sprite" => {
+            let (x, y) = (fields.number()?, fields.number()?);
+            let (width, height) = (fields.number()?, fields.number()?);
+            let (depth, scale) = (fields.number()?, fields.number()?);
+            let (flip_x, flip_y) = match fields.next()? {
+                "-" => (false, false),
+                "x" => (true, false),
+                "y" => (false, true),
+                "xy" => (true, true),
+                flip => return Err(format!("unknown flip {flip}")),
+            };
+            DrawCommand::Sprite(Sprite {
+                x,
+                y,
+                width,
+                height,
+                depth,
+                data: fields.list(|word| u64::from_str_radix(word, 16).ok())?,
+                colors: fields.list(parse_color)?,
+                flip_x,
+                flip_y,
+                scale,
+            })
+        }
+        _ => return Err(format!("unknown command {name}")),
+    };
+    match fields.0.next() {
+        Some(extra) => Err(format!("unexpected {extra}")),
+        None => Ok(command),
+    }
+}
+
+/// `text` ends with a quoted string, which may contain spaces.
+fn parse_text(rest: &str) -> Result<DrawCommand, String> {
+    let mut parts = rest.splitn(5, ' ');
+    let mut fields = Fields(parts.by_ref().take(4));
+    let (x, y, scale) = (fields.number()?, fields.number()?, fields.number()?);
+    let color = fields.color()?;
+    let quoted = parts.next().ok_or("missing text")?.trim();
+    Ok(DrawCommand::Text(Text {
+        x,
+        y,
+        text: unquote(quoted).ok_or_else(|| format!("bad string {quoted}"))?,
+        scale,
+        color,
+    }))
+}
+
+/// Reverses the `{:?}` formatting used for strings, for the escapes it
+/// produces for text that can appear in a frame.
+fn unquote(quoted: &str) -> Option<String> {
+    let inner = quoted.strip_prefix('"')?.strip_suffix('"')?;
+    let mut text = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        text.push(match chars.next()? {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            'u' => {
+                let code = chars.as_str().strip_prefix('{')?;
+                let (hex, rest) = code.split_once('}')?;
+                chars = rest.chars();
+                char::from_u32(u32::from_str_radix(hex, 16).ok()?)?
+            }
+            escaped => escaped,
+        });
+    }
+    Some(text)
+}
+
+fn parse_color(text: &str) -> Option<Color> {
+    let hex = text.strip_prefix('#')?;
+    if !matches!(hex.len(), 6 | 8) {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some(if hex.len() == 6 {
+        Color::from_rgb((value >> 16) as u8, (value >> 8) as u8, value as u8)
+    } else {
+        Color::from_rgba(
+            (value >> 24) as u8,
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            value as u8,
+        )
+    })
+}
+
+struct Fields<I>(I);
+
+impl<'a, I: Iterator<Item = &'a str>> Fields<I> {
+    fn next(&mut self) -> Result<&'a str, String> {
+        self.0.next().ok_or_else(|| "missing field".to_string())
+    }
+
+    fn number<N: std::str::FromStr>(&mut self) -> Result<N, String> {
+        let field = self.next()?;
+        field.parse().map_err(|_| format!("bad number {field}"))
+    }
+
+    fn color(&mut self) -> Result<Color, String> {
+        let field = self.next()?;
+        parse_color(field).ok_or_else(|| format!("bad colour {field}"))
+    }
+
+    fn style(&mut self) -> Result<Style, String> {
+        match self.next()? {
+            "fill" => Ok(Style::Fill),
+            "stroke" => Ok(Style::Stroke),
+            style => Err(format!("unknown style {style}")),
+        }
+    }
+
+    fn list<T>(&mut self, parse: impl Fn(&str) -> Option<T>) -> Result<Vec<T>, String> {
+        let field = self.next()?;
+        if field == "-" {
+            return Ok(Vec::new());
+        }
+        field
+            .split(',')
+            .map(|item| parse(item).ok_or_else(|| format!("bad item {item}")))
+            .collect()
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+const MIN_SPEED_SHIFT: i8 = -4;
+const MAX_SPEED_SHIFT: i8 = 3;
+
+/// Pause, single-step and speed control for stepping through a game.
+#[derive(Debug, Default)]
+pub struct Debugger {
+    paused: bool,
+    step_requested: bool,
+    /// Playback speed as a power of two, so `-1` is half speed.
+    speed_shift: i8,
+}
+
+impl Debugger {
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Advances exactly one step on the next tick while paused.
+    pub fn request_step(&mut self) {
+        self.step_requested = true;
+    }
+
+    pub fn slower(&mut self) {
+        self.speed_shift = (self.speed_shift - 1).max(MIN_SPEED_SHIFT);
+    }
+
+    pub fn faster(&mut self) {
+        self.speed_shift = (self.speed_shift + 1).min(MAX_SPEED_SHIFT);
+    }
+
+    /// Whether the game should step this tick.
+    pub fn should_step(&mut self) -> bool {
+        let step = !self.paused || self.step_requested;
+        self.step_requested = false;
+        step
+    }
+
+    /// Scales the game's natural frame duration by the current speed.
+    pub fn frame_duration(&self, base: Duration) -> Duration {
+        if self.speed_shift >= 0 {
+            base / (1 << self.speed_shift)
+        } else {
+            base * (1 << -self.speed_shift)
+        }
+    }
+
+    pub fn speed(&self) -> f32 {
+        2f32.powi(self.speed_shift.into())
+    }
+
+    pub fn title(&self, name: &str, frame: u64, state: u64) -> String {
+        let paused = if self.paused { " | paused" } else { "" };
+        format!(
+            "{name} | frame {frame} | state {state:#018x} | x{}{paused}",
+            self.speed()
+        )
+    }
+}
@@ -1,4 +1,6 @@
-#[derive(Clone, Copy, Debug)]
+use crate::bits;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -7,9 +9,33 @@ pub struct Color {
 }
 
 impl Color {
-    const fn from_rgb(r: u8, g: u8, b: u8) -> Self {
+    pub const fn from_rgb(r: u8, g: u8, b: u8) -> Self {
         Color { r, g, b, a: 255 }
     }
+
+    pub const fn from_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Color { r, g, b, a }
+    }
+
+    /// The same colour with a different opacity, e.g. `BLACK.with_alpha(128)`.
+    pub const fn with_alpha(self, a: u8) -> Self {
+        Color { a, ..self }
+    }
+}
+
+/// How a command's colour is combined with the pixels already drawn.
+///
+/// Every mode is weighted by the colour's alpha, so an alpha of 0 leaves the
+/// framebuffer untouched whatever the mode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Source-over compositing.
+    #[default]
+    Alpha,
+    /// Adds the colour to the pixel, saturating at white. Good for glows.
+    Add,
+    /// Multiplies the pixel by the colour. Good for shadows and tinting.
+    Multiply,
 }
 
 // PICO-8 Color Palette
@@ -32,35 +58,256 @@ pub const LAVENDER: Color = Color::from_rgb(131, 118, 156);
 pub const PINK: Color = Color::from_rgb(255, 119, 168);
 pub const LIGHT_PEACH: Color = Color::from_rgb(255, 204, 170);
 
-#[derive(Clone, Copy, Debug)]
+/// The colours above in PICO-8 index order.
+pub const PICO8_PALETTE: [Color; 16] = [
+    BLACK,
+    DARK_BLUE,
+    DARK_PURPLE,
+    DARK_GREEN,
+    BROWN,
+    DARK_GREY,
+    LIGHT_GREY,
+    WHITE,
+    RED,
+    ORANGE,
+    YELLOW,
+    GREEN,
+    BLUE,
+    LAVENDER,
+    PINK,
+    LIGHT_PEACH,
+];
+
+/// Whether a shape is filled in or only has its outline drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    Fill,
+    Stroke,
+}
+
+/// Restricts drawing to a rectangle of the framebuffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Clip {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Shapes use signed coordinates and are clipped to the framebuffer, so they
+/// may lie partly or wholly off screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Rectangle {
-    pub x: u32,
-    pub y: u32,
+    pub x: i32,
+    pub y: i32,
     pub width: u32,
     pub height: u32,
     pub color: Color,
+    pub style: Style,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Line {
-    pub x1: u32,
-    pub y1: u32,
-    pub x2: u32,
-    pub y2: u32,
+    pub x1: i32,
+    pub y1: i32,
+    pub x2: i32,
+    pub y2: i32,
     pub color: Color,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Circle {
-    pub x: u32,
-    pub y: u32,
+    pub x: i32,
+    pub y: i32,
     pub radius: u32,
     pub color: Color,
+    pub style: Style,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Triangle {
+    pub x1: i32,
+    pub y1: i32,
+    pub x2: i32,
+    pub y2: i32,
+    pub x3: i32,
+    pub y3: i32,
+    pub color: Color,
+    pub style: Style,
+}
+
+/// A closed polygon through `points`, which may be concave.
+///
+/// Filled polygons use the even-odd rule, so self-intersecting outlines
+/// leave holes where they overlap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polygon {
+    pub points: Vec<(i32, i32)>,
+    pub color: Color,
+    pub style: Style,
+}
+
+/// Text in the built-in 3x5 font, see [`crate::font`].
+///
+/// Each font pixel is drawn as a `scale` x `scale` block, and `\n` starts a
+/// new line below `x`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Text {
+    pub x: i32,
+    pub y: i32,
+    pub text: String,
+    pub scale: u32,
+    pub color: Color,
+}
+
+/// A bitmap packed into `u64`s, `depth` bits per pixel.
+///
+/// Pixels are stored row by row starting at bit 0 of `data[0]`, the same order
+/// the inspector shows state bits in, so an 8x8 one-bit sprite is exactly one
+/// `u64`. A pixel with value `n` is drawn in `colors[n - 1]`; 0 and values
+/// without a colour are transparent. `depth` must be 1, 2, 4 or 8 so pixels
+/// never straddle two words; sprites of any other depth draw nothing. Each
+/// pixel is drawn as a `scale` x `scale` block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sprite {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    pub data: Vec<u64>,
+    pub colors: Vec<Color>,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub scale: u32,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl Sprite {
+    /// An unflipped, unscaled 8x8 one-bit sprite drawn in `color`.
+    pub fn mask(x: i32, y: i32, bits: u64, color: Color) -> Self {
+        Sprite {
+            x,
+            y,
+            width: 8,
+            height: 8,
+            depth: 1,
+            data: vec![bits],
+            colors: vec![color],
+            flip_x: false,
+            flip_y: false,
+            scale: 1,
+        }
+    }
+
+    /// The value of the pixel at `x`, `y` before flipping, or 0 if the pixel
+    /// is outside the sprite or missing from `data`, or the depth is invalid.
+    pub fn value(&self, x: u32, y: u32) -> u8 {
+        if x >= self.width || y >= self.height || !matches!(self.depth, 1 | 2 | 4 | 8) {
+            return 0;
+        }
+        // Huge sprites can put a pixel's bit index past `u64::MAX`
+        let Some(bit) = u64::from(y)
+            .checked_mul(self.width.into())
+            .and_then(|pixel| pixel.checked_add(x.into()))
+            .and_then(|pixel| pixel.checked_mul(self.depth.into()))
+        else {
+            return 0;
+        };
+        let word = usize::try_from(bit / 64)
+            .ok()
+            .and_then(|i| self.data.get(i))
+            .copied()
+            .unwrap_or(0);
+        bits::get(word, (bit % 64) as u32, self.depth) as u8
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum DrawCommand {
+    /// Sets the blend mode for the commands after it.
+    BlendMode(BlendMode),
+    /// Clips the commands after it to a rectangle, or only to the framebuffer.
+    Clip(Option<Clip>),
     Rectangle(Rectangle),
     Line(Line),
     Circle(Circle),
+    Triangle(Triangle),
+    Polygon(Polygon),
+    Text(Text),
+    Sprite(Sprite),
+}
+
+impl DrawCommand {
+    /// The same command for a target `factor` times larger in each direction.
+    ///
+    /// Areas such as rectangles, text and sprites cover the scaled up block of
+    /// pixels they covered before. Points such as line ends and circle centres
+    /// move to the middle of their block, and outlines stay one pixel wide, so
+    /// shapes come out smoother rather than blockier.
+    pub fn scaled(&self, factor: u32) -> DrawCommand {
+        let factor = factor.max(1);
+        let edge = |v: i32| v.saturating_mul(factor as i32);
+        let point = |v: i32| edge(v).saturating_add((factor / 2) as i32);
+        let size = |v: u32| v.saturating_mul(factor);
+
+        match self {
+            DrawCommand::BlendMode(mode) => DrawCommand::BlendMode(*mode),
+            DrawCommand::Clip(clip) => DrawCommand::Clip(clip.map(|clip| Clip {
+                x: edge(clip.x),
+                y: edge(clip.y),
+                width: size(clip.width),
+                height: size(clip.height),
+            })),
+            DrawCommand::Rectangle(rect) => DrawCommand::Rectangle(Rectangle {
+                x: edge(rect.x),
+                y: edge(rect.y),
+                width: size(rect.width),
+                height: size(rect.height),
+                ..*rect
+            }),
+            DrawCommand::Line(line) => DrawCommand::Line(Line {
+                x1: point(line.x1),
+                y1: point(line.y1),
+                x2: point(line.x2),
+                y2: point(line.y2),
+                ..*line
+            }),
+            DrawCommand::Circle(circle) => DrawCommand::Circle(Circle {
+                x: point(circle.x),
+                y: point(circle.y),
+                radius: size(circle.radius).saturating_add(factor / 2),
+                ..*circle
+            }),
+            DrawCommand::Triangle(triangle) => DrawCommand::Triangle(Triangle {
+                x1: point(triangle.x1),
+                y1: point(triangle.y1),
+                x2: point(triangle.x2),
+                y2: point(triangle.y2),
+                x3: point(triangle.x3),
+                y3: point(triangle.y3),
+                ..*triangle
+            }),
+            DrawCommand::Polygon(polygon) => DrawCommand::Polygon(Polygon {
+                points: polygon
+                    .points
+                    .iter()
+                    .map(|&(x, y)| (point(x), point(y)))
+                    .collect(),
+                color: polygon.color,
+                style: polygon.style,
+            }),
+            DrawCommand::Text(text) => DrawCommand::Text(Text {
+                x: edge(text.x),
+                y: edge(text.y),
+                scale: size(text.scale.max(1)),
+                ..text.clone()
+            }),
+            DrawCommand::Sprite(sprite) => DrawCommand::Sprite(Sprite {
+                x: edge(sprite.x),
+                y: edge(sprite.y),
+                scale: size(sprite.scale.max(1)),
+                ..sprite.clone()
+            }),
+        }
+    }
 }
@@ -0,0 +1,98 @@
+//! A tiny 3x5 pixel font for drawing text on small boards.
+//!
+//! Each glyph is five rows of three bits, with the high bit on the left.
+//! Lowercase letters are drawn as uppercase and unknown characters as `?`.
+
+pub const GLYPH_WIDTH: u32 = 3;
+pub const GLYPH_HEIGHT: u32 = 5;
+/// Horizontal distance between the starts of consecutive characters.
+pub const ADVANCE: u32 = GLYPH_WIDTH + 1;
+/// Vertical distance between the tops of consecutive lines.
+pub const LINE_HEIGHT: u32 = GLYPH_HEIGHT + 1;
+
+pub fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '"' => [0b101, 0b101, 0b000, 0b000, 0b000],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '$' => [0b011, 0b110, 0b010, 0b011, 0b110],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '&' => [0b010, 0b101, 0b010, 0b101, 0b011],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '*' => [0b000, 0b101, 0b010, 0b101, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        ';' => [0b000, 0b010, 0b000, 0b010, 0b100],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '@' => [0b010, 0b101, 0b111, 0b100, 0b011],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b011],
+        'V' => [0b101, 0b101, 0b101, 0b010, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '[' => [0b011, 0b010, 0b010, 0b010, 0b011],
+        '\\' => [0b100, 0b100, 0b010, 0b001, 0b001],
+        ']' => [0b110, 0b010, 0b010, 0b010, 0b110],
+        '^' => [0b010, 0b101, 0b000, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '`' => [0b100, 0b010, 0b000, 0b000, 0b000],
+        '{' => [0b011, 0b010, 0b110, 0b010, 0b011],
+        '|' => [0b010, 0b010, 0b010, 0b010, 0b010],
+        '}' => [0b110, 0b010, 0b011, 0b010, 0b110],
+        '~' => [0b000, 0b011, 0b110, 0b000, 0b000],
+        _ => [0b111, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+/// Size in pixels of `text` drawn at `scale`, without trailing spacing.
+pub fn text_size(text: &str, scale: u32) -> (u32, u32) {
+    let lines = text.lines().count().max(1) as u32;
+    let columns = text
+        .lines()
+        .map(|line| line.chars().count() as u32)
+        .max()
+        .unwrap_or(0);
+    let width = (columns * ADVANCE).saturating_sub(1);
+    let height = lines * LINE_HEIGHT - 1;
+    (width * scale, height * scale)
+}
@@ -1,109 +1,123 @@
-use crate::draw_command::{Circle, Color, DrawCommand, Line, Rectangle};
+use crate::draw_command::{BlendMode, Clip, Color, DrawCommand};
+use crate::png;
+use crate::raster::{Bounds, Rasterizer};
+use std::fs;
+use std::io;
+use std::path::Path;
 
+/// Pixels in `0x00RRGGBB` form. Colours are composited on draw, so no alpha
+/// channel is stored.
 pub struct FrameBuffer {
     pub pixels: Vec<u32>,
     pub width: u32,
     pub height: u32,
+    blend_mode: BlendMode,
+    clip: Option<Clip>,
+    /// Per pixel, the last command that blended into it. Rasterizers may visit
+    /// a pixel more than once, which must not compound translucent colours.
+    stamps: Vec<u32>,
+    generation: u32,
 }
 
 impl FrameBuffer {
     pub fn new(width: u32, height: u32) -> Self {
+        Self::from_pixels(vec![0; (width * height) as usize], width, height)
+    }
+
+    /// Wraps existing pixels, e.g. to draw an overlay on a finished frame.
+    pub fn from_pixels(pixels: Vec<u32>, width: u32, height: u32) -> Self {
         Self {
-            pixels: vec![0; (width * height) as usize],
+            pixels,
             width,
             height,
+            blend_mode: BlendMode::default(),
+            clip: None,
+            stamps: Vec::new(),
+            generation: 0,
         }
     }
 
+    /// Draws one command. Blend modes and clips set by earlier commands stay
+    /// in effect until changed.
     pub fn draw(&mut self, command: &DrawCommand) {
+        self.generation = self.generation.wrapping_add(1);
+        if self.generation == 0 {
+            self.stamps.fill(0);
+            self.generation = 1;
+        }
+
         match command {
-            DrawCommand::Rectangle(rect) => self.draw_rectangle(rect),
-            DrawCommand::Line(line) => self.draw_line(line),
-            DrawCommand::Circle(circle) => self.draw_circle(circle),
+            DrawCommand::BlendMode(mode) => self.blend_mode = *mode,
+            DrawCommand::Clip(clip) => self.clip = *clip,
+            shape => {
+                let bounds = Bounds::new(self.width, self.height, self.clip);
+                Rasterizer::new(bounds, |x, y, color: &Color| self.set_pixel(x, y, color))
+                    .draw(shape);
+            }
         }
     }
 
+    /// Draws a list of commands, starting from alpha blending and no clip
+    /// whatever an earlier list left behind.
     pub fn draw_list(&mut self, commands: &[DrawCommand]) {
+        self.blend_mode = BlendMode::default();
+        self.clip = None;
         for command in commands {
             self.draw(command);
         }
     }
 
-    fn draw_rectangle(&mut self, rect: &Rectangle) {
-        for y in rect.y..(rect.y + rect.height) {
-            for x in rect.x..(rect.x + rect.width) {
-                self.set_pixel(x, y, &rect.color);
-            }
-        }
+    /// Encodes the pixels as a PNG file.
+    pub fn to_png(&self) -> Vec<u8> {
+        png::encode(&self.pixels, self.width, self.height)
     }
 
-    fn draw_line(&mut self, line: &Line) {
-        // Bresenham's line algorithm
-        let mut x0 = line.x1 as i32;
-        let mut y0 = line.y1 as i32;
-        let x1 = line.x2 as i32;
-        let y1 = line.y2 as i32;
-        let dx = (x1 - x0).abs();
-        let sx = if x0 < x1 { 1 } else { -1 };
-        let dy = -(y1 - y0).abs();
-        let sy = if y0 < y1 { 1 } else { -1 };
-        let mut err = dx + dy;
-
-        loop {
-            self.set_pixel(x0 as u32, y0 as u32, &line.color);
-            if x0 == x1 && y0 == y1 {
-                break;
-            }
-            let e2 = 2 * err;
-            if e2 >= dy {
-                err += dy;
-                x0 += sx;
-            }
-            if e2 <= dx {
-                err += dx;
-                y0 += sy;
-            }
-        }
+    pub fn write_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_png())
     }
 
-    fn draw_circle(&mut self, circle: &Circle) {
-        // Midpoint circle algorithm
-        let x_center = circle.x as i32;
-        let y_center = circle.y as i32;
-        let mut x = circle.radius as i32;
-        let mut y = 0;
-        let mut err = 0;
-
-        while x >= y {
-            self.set_pixel((x_center + x) as u32, (y_center + y) as u32, &circle.color);
-            self.set_pixel((x_center + y) as u32, (y_center + x) as u32, &circle.color);
-            self.set_pixel((x_center - y) as u32, (y_center + x) as u32, &circle.color);
-            self.set_pixel((x_center - x) as u32, (y_center + y) as u32, &circle.color);
-            self.set_pixel((x_center - x) as u32, (y_center - y) as u32, &circle.color);
-            self.set_pixel((x_center - y) as u32, (y_center - x) as u32, &circle.color);
-            self.set_pixel((x_center + y) as u32, (y_center - x) as u32, &circle.color);
-            self.set_pixel((x_center + x) as u32, (y_center - y) as u32, &circle.color);
+    /// Blends one pixel. The rasterizer clips first, the bounds check is a backstop.
+    fn set_pixel(&mut self, x: i64, y: i64, color: &Color) {
+        if x < 0 || y < 0 || x >= i64::from(self.width) || y >= i64::from(self.height) {
+            return;
+        }
+        let index = (y * i64::from(self.width) + x) as usize;
+        if index >= self.pixels.len() {
+            return;
+        }
 
-            if err <= 0 {
-                y += 1;
-                err += 2 * y + 1;
-            }
-            if err > 0 {
-                x -= 1;
-                err -= 2 * x + 1;
-            }
+        if color.a == 255 && self.blend_mode == BlendMode::Alpha {
+            self.pixels[index] =
+                ((color.r as u32) << 16) | ((color.g as u32) << 8) | (color.b as u32);
+            return;
+        }
+        if color.a == 0 {
+            return;
         }
-    }
 
-    fn set_pixel(&mut self, x: u32, y: u32, color: &Color) {
-        if x < self.width && y < self.height {
-            let index = (y * self.width + x) as usize;
-            if index < self.pixels.len() {
-                self.pixels[index] = ((color.a as u32) << 24)
-                    | ((color.r as u32) << 16)
-                    | ((color.g as u32) << 8)
-                    | (color.b as u32);
-            }
+        // Translucent blends are not idempotent, so blend each pixel once per command
+        if self.stamps.len() != self.pixels.len() {
+            self.stamps = vec![0; self.pixels.len()];
+        }
+        if self.stamps[index] == self.generation {
+            return;
         }
+        self.stamps[index] = self.generation;
+
+        let pixel = self.pixels[index];
+        let blend = |shift: u32, source: u8| {
+            let dest = (pixel >> shift) & 0xff;
+            blend_channel(self.blend_mode, dest, source as u32, color.a as u32) << shift
+        };
+        self.pixels[index] = blend(16, color.r) | blend(8, color.g) | blend(0, color.b);
     }
 }
+
+fn blend_channel(mode: BlendMode, dest: u32, source: u32, alpha: u32) -> u32 {
+    let target = match mode {
+        BlendMode::Alpha => source,
+        BlendMode::Add => (dest + source).min(255),
+        BlendMode::Multiply => (dest * source + 127) / 255,
+    };
+    (target * alpha + dest * (255 - alpha) + 127) / 255
+}
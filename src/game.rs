@@ -1,11 +1,35 @@
-use minifb::Key;
+use crate::draw_command::DrawCommand;
+use crate::input::Input;
+use crate::layout::Field;
+use crate::output::Sound;
 
 pub trait Game {
     const NAME: &'static str;
     const FPS: usize;
+    /// Logical resolution `render` draws at.
     const WIDTH: usize;
     const HEIGHT: usize;
+    /// Initial window size. The runner scales frames up to fit the window.
+    const WINDOW_WIDTH: usize = Self::WIDTH;
+    const WINDOW_HEIGHT: usize = Self::HEIGHT;
+    /// Field layout of the state, used by the bit inspector to label bits.
+    const LAYOUT: &'static [Field] = &[];
 
-    fn new(args: Vec<String>) -> (u64, Vec<u32>);
-    fn update(state: u64, keys: &[Key]) -> (u64, Vec<u32>);
+    /// The initial state, given the command line arguments left over by the runner.
+    #[allow(clippy::new_ret_no_self)]
+    fn new(args: Vec<String>) -> u64;
+    /// The state one frame later.
+    fn step(state: u64, input: Input) -> u64;
+    /// Draws a state as a list of commands, which the runner rasterizes at
+    /// whatever resolution it needs. Only called for frames that are shown or
+    /// captured, so simulating without a screen never renders.
+    fn render(state: u64) -> Vec<DrawCommand>;
+
+    /// Sounds to start on the frame that went from `previous` to `next`.
+    ///
+    /// Working them out from the two states keeps `step` free of side effects and
+    /// means replays sound the same as the session they recorded.
+    fn sounds(_previous: u64, _next: u64) -> Vec<Sound> {
+        Vec::new()
+    }
 }
@@ -0,0 +1,455 @@
+//! Animated GIF encoding.
+//!
+//! Frames are mapped to a small palette, PICO-8's by default, and LZW
+//! compressed. Games drawn with a handful of flat colours compress very well.
+
+use crate::draw_command::{Color, PICO8_PALETTE};
+use crate::game::Game;
+use crate::replay::Replay;
+use crate::runner;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// GIF delays are in hundredths of a second, and most viewers slow down
+/// anything shorter than this, so faster frames are dropped.
+const MIN_DELAY: u64 = 2;
+
+const MAX_CODE: u16 = 4095;
+
+/// An animated GIF that frames are appended to one at a time.
+///
+/// Frames are timed from the frame rate. Identical consecutive frames are
+/// merged into one longer frame.
+pub struct Gif {
+    width: u16,
+    height: u16,
+    fps: u64,
+    min_code_size: u8,
+    palette: Vec<Color>,
+    nearest: HashMap<u32, u8>,
+    bytes: Vec<u8>,
+    frames: u64,
+    /// The last frame and the frame number it started on. It is written once
+    /// the next different frame shows how long it lasted.
+    pending: Option<(Vec<u8>, u64)>,
+}
+
+impl Gif {
+    /// A GIF using the PICO-8 palette.
+    pub fn new(width: u32, height: u32, fps: u32) -> Self {
+        Self::with_palette(width, height, fps, PICO8_PALETTE.to_vec())
+    }
+
+    /// A GIF using the first 256 colours of `palette`.
+    pub fn with_palette(width: u32, height: u32, fps: u32, mut palette: Vec<Color>) -> Self {
+        palette.truncate(256);
+        let table_bits = palette.len().next_power_of_two().trailing_zeros().max(1);
+        let width = width.min(u16::MAX.into()) as u16;
+        let height = height.min(u16::MAX.into()) as u16;
+
+        let mut bytes = b"GIF89a".to_vec();
+        bytes.extend_from_slice(&width.to_le_bytes());
+        bytes.extend_from_slice(&height.to_le_bytes());
+        // Global colour table of 2^table_bits 8-bit entries
+        bytes.push(0x80 | 0x70 | (table_bits as u8 - 1));
+        bytes.extend_from_slice(&[0, 0]);
+        for i in 0..1 << table_bits {
+            let color = palette.get(i).copied().unwrap_or(PICO8_PALETTE[0]);
+            bytes.extend_from_slice(&[color.r, color.g, color.b]);
+        }
+        // Loop forever
+        bytes.extend_from_slice(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+
+        Self {
+            width,
+            height,
+            fps: u64::from(fps.max(1)),
+            min_code_size: table_bits.max(2) as u8,
+            palette,
+            nearest: HashMap::new(),
+            bytes,
+            frames: 0,
+            pending: None,
+        }
+    }
+
+    /// The number of frames pushed so far, including merged and dropped ones.
+    pub fn len(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    /// Appends `0x00RRGGBB` pixels, each drawn in the nearest palette colour.
+    pub fn push_pixels(&mut self, pixels: &[u32]) {
+        let indices = pixels
+            .iter()
+            .map(|&pixel| {
+                *self
+                    .nearest
+                    .entry(pixel)
+                    .or_insert_with(|| nearest(&self.palette, pixel))
+            })
+            .collect();
+        self.push_indices(indices);
+    }
+
+    /// Appends a frame of palette indices, such as an
+    /// [`IndexedFrameBuffer`](crate::indexed::IndexedFrameBuffer)'s.
+    pub fn push_indices(&mut self, mut indices: Vec<u8>) {
+        indices.resize(usize::from(self.width) * usize::from(self.height), 0);
+        let frame = self.frames;
+        self.frames += 1;
+
+        match self.pending.take() {
+            Some((pending, start)) if pending == indices => {
+                self.pending = Some((pending, start));
+            }
+            Some((_, start)) if self.centiseconds(frame) - self.centiseconds(start) < MIN_DELAY => {
+                self.pending = Some((indices, start));
+            }
+            Some((pending, start)) => {
+                let delay = self.centiseconds(frame) - self.centiseconds(start);
+                self.write_frame(&pending, delay);
+                self.pending = Some((indices, frame));
+            }
+            None => self.pending = Some((indices, frame)),
+        }
+    }
+
+    /// Writes the last frame and returns the finished file.
+    pub fn finish(mut self) -> Vec<u8> {
+        if let Some((pending, start)) = self.pending.take() {
+            let delay = self.centiseconds(self.frames) - self.centiseconds(start);
+            self.write_frame(&pending, delay.max(MIN_DELAY));
+        }
+        self.bytes.push(0x3b);
+        self.bytes
+    }
+
+    pub fn save(self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.finish())
+    }
+
+    /// Renders a replay without a window, one frame per recorded input.
+    pub fn from_replay<T: Game>(replay: &Replay) -> Self {
+        let mut gif = Self::new(T::WIDTH as u32, T::HEIGHT as u32, T::FPS as u32);
+        let mut state = replay.initial_state;
+        for &input in &replay.inputs {
+            state = T::step(state, input);
+            gif.push_pixels(&runner::rasterize::<T>(&T::render(state), 1));
+        }
+        gif
+    }
+
+    /// When frame `frame` starts, rounded to hundredths of a second.
+    fn centiseconds(&self, frame: u64) -> u64 {
+        (frame * 100 + self.fps / 2) / self.fps
+    }
+
+    fn write_frame(&mut self, indices: &[u8], delay: u64) {
+        let delay = delay.min(u16::MAX.into()) as u16;
+        // Graphic control extension: keep the frame, no transparency
+        self.bytes.extend_from_slice(&[0x21, 0xf9, 0x04, 0x04]);
+        self.bytes.extend_from_slice(&delay.to_le_bytes());
+        self.bytes.extend_from_slice(&[0, 0]);
+
+        // Image descriptor covering the whole canvas, using the global palette
+        self.bytes.push(0x2c);
+        self.bytes.extend_from_slice(&[0, 0, 0, 0]);
+        self.bytes.extend_from_slice(&self.width.to_le_bytes());
+        self.bytes.extend_from_slice(&self.height.to_le_bytes());
+        self.bytes.push(0);
+
+        self.bytes.push(self.min_code_size);
+        let data = lzw(indices, self.min_code_size);
+        for block in data.chunks(255) {
+            self.bytes.push(block.len() as u8);
+            self.bytes.extend_from_slice(block);
+        }
+        self.bytes.push(0);
+    }
+}
+
+fn nearest(palette: &[Color], pixel: u32) -> u8 {
+    let channel = |shift: u32| ((pixel >> shift) & 0xff) as i32;
+    let distance = |color: &Color| {
+        let dr = i32::from(color.r) - channel(16);
+        let dg = i32::from(color.g) - channel(8);
+        let db = i32::from(color.b) - channel(0);
+        dr * dr + dg * dg + db * db
+    };
+    (0..=u8::MAX)
+        .zip(palette)
+        .min_by_key(|(_, color)| distance(color))
+        .map_or(0, |(index, _)| index)
+}
+
+/// GIF flavoured LZW: variable width codes packed least significant bit
+/// first, growing up to 12 bits and starting over when the table is full.
+fn lzw(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let mut writer = BitWriter::default();
+    let mut table = HashMap::new();
+    let mut code_size = min_code_size + 1;
+    let mut next_code = end + 1;
+
+    writer.write(clear, code_size);
+    let mut prefix: Option<u16> = None;
+    for &index in indices {
+        let index = index & (clear - 1) as u8;
+        let Some(code) = prefix else {
+            prefix = Some(index.into());
+            continue;
+        };
+        if let Some(&longer) = table.get(&(code, index)) {
+            prefix = Some(longer);
+            continue;
+        }
+        writer.write(code, code_size);
+        if next_code <= MAX_CODE {
+            table.insert((code, index), next_code);
+            widen(&mut next_code, &mut code_size);
+        } else {
+            writer.write(clear, code_size);
+            table.clear();
+            code_size = min_code_size + 1;
+            next_code = end + 1;
+        }
+        prefix = Some(index.into());
+    }
+    if let Some(code) = prefix {
+        writer.write(code, code_size);
+        // The decoder adds an entry for this code too, which may widen the end code
+        if next_code <= MAX_CODE {
+            widen(&mut next_code, &mut code_size);
+        }
+    }
+    writer.write(end, code_size);
+    writer.finish()
+}
+
+/// Uses up `next_code`. Decoders widen codes one entry behind the encoder, so
+/// the width only grows once a code that needs it has been handed out.
+fn widen(next_code: &mut u16, code_size: &mut u8) {
+    if *next_code == 1 << *code_size && *code_size < 12 {
+        *code_size += 1;
+    }
+    *next_code += 1;
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u8) {
+        self.buffer |= u32::from(code) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic xorshift noise, the worst case for LZW.
+    fn noise(len: usize, colors: u8, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state % u64::from(colors)) as u8
+            })
+            .collect()
+    }
+
+    /// A plain GIF LZW decoder, written from the spec rather than from the
+    /// encoder. Checks that every bit of `data` is used.
+    fn unlzw(data: &[u8], min_code_size: u8) -> Vec<u8> {
+        unlzw_with_boundaries(data, min_code_size).0
+    }
+
+    /// Also returns how much had been decoded at every width change and at
+    /// every table reset, where an encoder is most likely to go wrong.
+    fn unlzw_with_boundaries(data: &[u8], min_code_size: u8) -> (Vec<u8>, Vec<usize>, Vec<usize>) {
+        let clear = 1usize << min_code_size;
+        let end = clear + 1;
+        let reset = || -> Vec<Vec<u8>> { (0..clear + 2).map(|i| vec![i as u8]).collect() };
+        let mut table = reset();
+        let mut code_size = min_code_size + 1;
+        let mut position = 0;
+        let mut previous: Option<Vec<u8>> = None;
+        let mut out = Vec::new();
+        let mut widths = Vec::new();
+        let mut resets = Vec::new();
+
+        loop {
+            let mut code = 0;
+            for bit in 0..code_size {
+                let byte = *data.get(position / 8).expect("no end code");
+                code |= usize::from((byte >> (position % 8)) & 1) << bit;
+                position += 1;
+            }
+            if code == clear {
+                resets.push(out.len());
+                table = reset();
+                code_size = min_code_size + 1;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                break;
+            }
+
+            let entry = match (table.get(code), &previous) {
+                (Some(entry), _) if code != clear && code != end => entry.clone(),
+                (None, Some(previous)) if code == table.len() => {
+                    [previous.as_slice(), &previous[..1]].concat()
+                }
+                _ => panic!("bad code {code} with {} entries", table.len()),
+            };
+            if let Some(previous) = previous
+                && table.len() < 4096
+            {
+                table.push([previous.as_slice(), &entry[..1]].concat());
+            }
+            out.extend_from_slice(&entry);
+            if table.len() == 1 << code_size && code_size < 12 {
+                widths.push(out.len());
+                code_size += 1;
+            }
+            previous = Some(entry);
+        }
+        assert_eq!(
+            position.div_ceil(8),
+            data.len(),
+            "bits left after the end code"
+        );
+        (out, widths, resets)
+    }
+
+    fn round_trip(indices: &[u8], min_code_size: u8) {
+        let decoded = unlzw(&lzw(indices, min_code_size), min_code_size);
+        assert!(
+            decoded == indices,
+            "{} indices with min code size {min_code_size} decoded to {}",
+            indices.len(),
+            decoded.len()
+        );
+    }
+
+    #[test]
+    fn lzw_round_trips_inputs_ending_at_every_boundary() {
+        // Cutting the input off just before, at and after each width change
+        // and the first table reset checks the widths of the last code and the
+        // end code
+        for (min_code_size, colors) in [(2, 4), (4, 16), (8, 255)] {
+            let indices = noise(30_000, colors, 1);
+            let (decoded, widths, resets) =
+                unlzw_with_boundaries(&lzw(&indices, min_code_size), min_code_size);
+            assert!(decoded == indices);
+            // The clear code at the very start doesn't count
+            let reset = *resets.get(1).expect("no table reset");
+            let boundaries = widths.into_iter().filter(|&width| width < reset);
+            for boundary in boundaries.chain([reset]) {
+                for len in boundary - 4..boundary + 4 {
+                    round_trip(&indices[..len], min_code_size);
+                }
+            }
+        }
+        for len in 0..64 {
+            round_trip(&noise(len, 4, 2), 2);
+        }
+    }
+
+    #[test]
+    fn lzw_round_trips_past_the_table_reset() {
+        // Noise uses a code every index or two, so these fill the 4096 entry
+        // table several times over
+        round_trip(&noise(128 * 128, 255, 2), 8);
+        round_trip(&noise(128 * 128, 16, 3), 4);
+        round_trip(&noise(200 * 200, 2, 4), 2);
+        // Long runs make long codes instead
+        round_trip(&vec![3; 1 << 20], 2);
+        let stripes: Vec<u8> = (0..100_000).map(|i| (i / 37 % 16) as u8).collect();
+        round_trip(&stripes, 4);
+    }
+
+    /// Joins the data sub-blocks at the start of `rest`, returning what follows.
+    fn sub_blocks(mut rest: &[u8]) -> (Vec<u8>, &[u8]) {
+        let mut data = Vec::new();
+        while rest[0] != 0 {
+            let len = usize::from(rest[0]);
+            data.extend_from_slice(&rest[1..1 + len]);
+            rest = &rest[1 + len..];
+        }
+        (data, &rest[1..])
+    }
+
+    /// The image data of every frame in a GIF written by [`Gif`].
+    fn frames(gif: &[u8]) -> Vec<Vec<u8>> {
+        let table_bits = (gif[10] & 0x07) + 1;
+        let mut rest = &gif[13 + 3 * (1 << table_bits)..];
+        let mut frames = Vec::new();
+        loop {
+            match rest[0] {
+                0x21 => (_, rest) = sub_blocks(&rest[2..]),
+                0x2c => {
+                    let min_code_size = rest[10];
+                    let data;
+                    (data, rest) = sub_blocks(&rest[11..]);
+                    frames.push(unlzw(&data, min_code_size));
+                }
+                0x3b => return frames,
+                byte => panic!("unexpected block {byte:#x}"),
+            }
+        }
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let (width, height) = (150, 100);
+        let mut gif = Gif::new(width, height, 10);
+        let pushed: Vec<Vec<u8>> = (0..4)
+            .map(|seed| noise((width * height) as usize, 16, seed + 10))
+            .collect();
+        for frame in &pushed {
+            gif.push_indices(frame.clone());
+        }
+        assert_eq!(frames(&gif.finish()), pushed);
+    }
+
+    #[test]
+    fn identical_frames_are_merged() {
+        let mut gif = Gif::new(8, 8, 30);
+        let frame = noise(64, 16, 20);
+        for _ in 0..5 {
+            gif.push_indices(frame.clone());
+        }
+        assert_eq!(gif.len(), 5);
+        assert_eq!(frames(&gif.finish()), [frame]);
+    }
+}
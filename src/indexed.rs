@@ -0,0 +1,197 @@
+use crate::draw_command::{Clip, Color, DrawCommand, PICO8_PALETTE};
+use crate::raster::{Bounds, Rasterizer};
+
+const IDENTITY: [u8; 256] = {
+    let mut map = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        map[i] = i as u8;
+        i += 1;
+    }
+    map
+};
+
+/// A framebuffer of 8-bit palette indices, a quarter the size of a
+/// [`FrameBuffer`](crate::frame_buffer::FrameBuffer).
+///
+/// Draw commands use the palette entry closest to their colour, so the PICO-8
+/// colour constants land on their own index. Indices pass through `draw_map`
+/// as they are drawn, like PICO-8's `pal`, and through `screen_map` when the
+/// frame is presented, which makes whole-screen effects such as flashing a
+/// matter of changing a few bytes. Colours are not blended: anything with a
+/// non-zero alpha is drawn opaque.
+pub struct IndexedFrameBuffer {
+    pub indices: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub palette: Vec<Color>,
+    pub draw_map: [u8; 256],
+    pub screen_map: [u8; 256],
+    clip: Option<Clip>,
+}
+
+impl IndexedFrameBuffer {
+    /// A framebuffer using the PICO-8 palette.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::with_palette(width, height, PICO8_PALETTE.to_vec())
+    }
+
+    pub fn with_palette(width: u32, height: u32, palette: Vec<Color>) -> Self {
+        Self {
+            indices: vec![0; (width * height) as usize],
+            width,
+            height,
+            palette,
+            draw_map: IDENTITY,
+            screen_map: IDENTITY,
+            clip: None,
+        }
+    }
+
+    pub fn clear(&mut self, index: u8) {
+        self.indices.fill(self.draw_map[usize::from(index)]);
+    }
+
+    pub fn set_pixel(&mut self, x: i32, y: i32, index: u8) {
+        self.plot(x.into(), y.into(), index);
+    }
+
+    pub fn get_pixel(&self, x: i32, y: i32) -> Option<u8> {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return None;
+        }
+        self.indices
+            .get(y as usize * self.width as usize + x as usize)
+            .copied()
+    }
+
+    /// Draws `from` as `to` from now on, until [`IndexedFrameBuffer::reset_maps`].
+    pub fn remap(&mut self, from: u8, to: u8) {
+        self.draw_map[usize::from(from)] = to;
+    }
+
+    /// Shows `from` as `to` when presenting, whatever was drawn before.
+    pub fn remap_screen(&mut self, from: u8, to: u8) {
+        self.screen_map[usize::from(from)] = to;
+    }
+
+    pub fn reset_maps(&mut self) {
+        self.draw_map = IDENTITY;
+        self.screen_map = IDENTITY;
+    }
+
+    /// The palette index whose colour is closest to `color`.
+    pub fn index_of(&self, color: &Color) -> u8 {
+        let distance = |entry: &Color| {
+            let dr = i32::from(entry.r) - i32::from(color.r);
+            let dg = i32::from(entry.g) - i32::from(color.g);
+            let db = i32::from(entry.b) - i32::from(color.b);
+            dr * dr + dg * dg + db * db
+        };
+        (0..=u8::MAX)
+            .zip(&self.palette)
+            .min_by_key(|(_, entry)| distance(entry))
+            .map_or(0, |(index, _)| index)
+    }
+
+    /// Draws one command. A clip set by an earlier command stays in effect
+    /// until changed.
+    pub fn draw(&mut self, command: &DrawCommand) {
+        match command {
+            DrawCommand::BlendMode(_) => {}
+            DrawCommand::Clip(clip) => self.clip = *clip,
+            shape => {
+                let bounds = Bounds::new(self.width, self.height, self.clip);
+                let mut last: Option<(Color, u8)> = None;
+                Rasterizer::new(bounds, |x, y, color: &Color| {
+                    if color.a == 0 {
+                        return;
+                    }
+                    let index = match last {
+                        Some((last_color, index)) if last_color == *color => index,
+                        _ => {
+                            let index = self.index_of(color);
+                            last = Some((*color, index));
+                            index
+                        }
+                    };
+                    self.plot(x, y, index);
+                })
+                .draw(shape);
+            }
+        }
+    }
+
+    /// Draws a list of commands, starting unclipped whatever an earlier list
+    /// left behind.
+    pub fn draw_list(&mut self, commands: &[DrawCommand]) {
+        self.clip = None;
+        for command in commands {
+            self.draw(command);
+        }
+    }
+
+    /// The indices packed two to a byte, the left pixel in the low nibble, and
+    /// each row starting on a new byte. Only the low four bits of each index
+    /// are kept, which is all a 16 colour palette needs.
+    pub fn to_nibbles(&self) -> Vec<u8> {
+        let row_bytes = (self.width as usize).div_ceil(2);
+        let mut nibbles = vec![0; row_bytes * self.height as usize];
+        for (row, packed) in self
+            .indices
+            .chunks(self.width.max(1) as usize)
+            .zip(nibbles.chunks_mut(row_bytes.max(1)))
+        {
+            for (pair, byte) in row.chunks(2).zip(packed) {
+                *byte = (pair[0] & 0x0f) | pair.get(1).map_or(0, |index| index << 4);
+            }
+        }
+        nibbles
+    }
+
+    /// A framebuffer using the PICO-8 palette with the indices packed by
+    /// [`IndexedFrameBuffer::to_nibbles`]. Missing bytes read as index 0.
+    pub fn from_nibbles(width: u32, height: u32, nibbles: &[u8]) -> Self {
+        let mut fb = Self::new(width, height);
+        let row_bytes = (width as usize).div_ceil(2);
+        for (y, row) in fb.indices.chunks_mut(width.max(1) as usize).enumerate() {
+            for (x, index) in row.iter_mut().enumerate() {
+                let byte = nibbles.get(y * row_bytes + x / 2).copied().unwrap_or(0);
+                *index = if x % 2 == 0 { byte & 0x0f } else { byte >> 4 };
+            }
+        }
+        fb
+    }
+
+    /// Resolves the indices to `0x00RRGGBB` pixels, as a `FrameBuffer` stores them.
+    pub fn present(&self) -> Vec<u32> {
+        let mut pixels = vec![0; self.indices.len()];
+        self.present_into(&mut pixels);
+        pixels
+    }
+
+    pub fn present_into(&self, pixels: &mut [u32]) {
+        let lookup: Vec<u32> = (0..=u8::MAX)
+            .map(|index| {
+                let color = self
+                    .palette
+                    .get(usize::from(self.screen_map[usize::from(index)]))
+                    .unwrap_or(&PICO8_PALETTE[0]);
+                (u32::from(color.r) << 16) | (u32::from(color.g) << 8) | u32::from(color.b)
+            })
+            .collect();
+        for (pixel, &index) in pixels.iter_mut().zip(&self.indices) {
+            *pixel = lookup[usize::from(index)];
+        }
+    }
+
+    fn plot(&mut self, x: i64, y: i64, index: u8) {
+        if x < 0 || y < 0 || x >= i64::from(self.width) || y >= i64::from(self.height) {
+            return;
+        }
+        let i = (y * i64::from(self.width) + x) as usize;
+        if let Some(pixel) = self.indices.get_mut(i) {
+            *pixel = self.draw_map[usize::from(index)];
+        }
+    }
+}
@@ -0,0 +1,79 @@
+/// A button on the virtual controller every game reads from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Button {
+    Left,
+    Right,
+    Up,
+    Down,
+    A,
+    B,
+    Start,
+    Select,
+}
+
+impl Button {
+    pub const ALL: [Button; 8] = [
+        Button::Left,
+        Button::Right,
+        Button::Up,
+        Button::Down,
+        Button::A,
+        Button::B,
+        Button::Start,
+        Button::Select,
+    ];
+
+    const fn mask(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// The set of buttons held down during a frame, packed one bit per button.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Input(u8);
+
+impl Input {
+    pub const NONE: Input = Input(0);
+
+    pub const fn from_bits(bits: u8) -> Self {
+        Input(bits)
+    }
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn is_pressed(self, button: Button) -> bool {
+        self.0 & button.mask() != 0
+    }
+
+    pub const fn with(self, button: Button) -> Self {
+        Input(self.0 | button.mask())
+    }
+
+    pub const fn without(self, button: Button) -> Self {
+        Input(self.0 & !button.mask())
+    }
+
+    pub fn pressed(self) -> impl Iterator<Item = Button> {
+        Button::ALL
+            .into_iter()
+            .filter(move |&button| self.is_pressed(button))
+    }
+}
+
+impl From<Button> for Input {
+    fn from(button: Button) -> Self {
+        Input::NONE.with(button)
+    }
+}
+
+impl FromIterator<Button> for Input {
+    fn from_iter<I: IntoIterator<Item = Button>>(iter: I) -> Self {
+        iter.into_iter().fold(Input::NONE, Input::with)
+    }
+}
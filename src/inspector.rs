@@ -0,0 +1,149 @@
+use crate::bits;
+use crate::draw_command::{
+    BLACK, BLUE, Color, DARK_GREY, DrawCommand, GREEN, LIGHT_GREY, ORANGE, PINK, RED, Rectangle,
+    Style, Text, WHITE, YELLOW,
+};
+use crate::font;
+use crate::frame_buffer::FrameBuffer;
+use crate::layout::Field;
+
+const FIELD_COLORS: [Color; 6] = [RED, ORANGE, YELLOW, GREEN, BLUE, PINK];
+
+/// Shows the 64 bits of the state as an 8x8 grid, bit 0 at the top left.
+///
+/// Set bits are filled with the colour of the field they belong to, every
+/// field is underlined in its colour and labelled with its decoded value, and
+/// bits that changed on the last step are outlined.
+#[derive(Debug, Default)]
+pub struct Inspector {
+    /// The frame last observed, `None` until the first observation.
+    frame: Option<u64>,
+    state: u64,
+    changed: u64,
+}
+
+impl Inspector {
+    /// Tracks which bits changed, once per simulated frame. The first
+    /// observation only records the state, so nothing starts out as changed.
+    pub fn observe(&mut self, frame: u64, state: u64) {
+        if self.frame.is_some_and(|last| last != frame) {
+            self.changed = self.state ^ state;
+        }
+        self.frame = Some(frame);
+        self.state = state;
+    }
+
+    /// The bits that changed on the last simulated frame.
+    pub fn changed(&self) -> u64 {
+        self.changed
+    }
+
+    pub fn draw(&self, fb: &mut FrameBuffer, layout: &[Field]) {
+        let cell = (fb.width.min(fb.height) / 32).max(2);
+        let grid = cell * 8;
+        let origin_x = fb.width as i32 - (grid + cell) as i32;
+        let origin_y = fb.height as i32 - (grid + cell) as i32;
+
+        let labels = self.labels(layout);
+        let scale = (cell / 10).max(1);
+        let labels_width = labels
+            .iter()
+            .map(|label| font::text_size(label, scale).0)
+            .max()
+            .map_or(0, |width| width + cell);
+        let labels_x = origin_x - labels_width as i32;
+
+        let mut commands = vec![DrawCommand::Rectangle(Rectangle {
+            x: labels_x - (cell / 2) as i32,
+            y: origin_y - (cell / 2) as i32,
+            width: labels_width + grid + cell,
+            height: grid + cell,
+            color: DARK_GREY,
+            style: Style::Fill,
+        })];
+
+        for (i, label) in labels.into_iter().enumerate() {
+            commands.push(DrawCommand::Text(Text {
+                x: labels_x,
+                y: origin_y + (i as u32 * font::LINE_HEIGHT * scale) as i32,
+                text: label,
+                scale,
+                color: FIELD_COLORS[i % FIELD_COLORS.len()],
+            }));
+        }
+
+        for bit in 0..64 {
+            let x = origin_x + ((bit % 8) * cell) as i32;
+            let y = origin_y + ((bit / 8) * cell) as i32;
+            let field_color =
+                field_index(layout, bit).map(|i| FIELD_COLORS[i % FIELD_COLORS.len()]);
+
+            let color = match (bits::get_flag(self.state, bit), field_color) {
+                (true, Some(color)) => color,
+                (true, None) => LIGHT_GREY,
+                (false, _) => BLACK,
+            };
+            commands.push(DrawCommand::Rectangle(Rectangle {
+                x: x + 1,
+                y: y + 1,
+                width: cell - 2,
+                height: cell - 2,
+                color,
+                style: Style::Fill,
+            }));
+
+            if let Some(color) = field_color {
+                commands.push(DrawCommand::Rectangle(Rectangle {
+                    x: x + 1,
+                    y: y + cell as i32 - 2,
+                    width: cell - 2,
+                    height: 1,
+                    color,
+                    style: Style::Fill,
+                }));
+            }
+
+            if bits::get_flag(self.changed, bit) {
+                commands.push(DrawCommand::Rectangle(Rectangle {
+                    x,
+                    y,
+                    width: cell,
+                    height: cell,
+                    color: WHITE,
+                    style: Style::Stroke,
+                }));
+            }
+        }
+
+        fb.draw_list(&commands);
+    }
+
+    /// Decodes each field of the state, e.g. `paddle_pos=26 ball_vel=1`.
+    pub fn describe(&self, layout: &[Field]) -> String {
+        self.labels(layout).join(" ")
+    }
+
+    fn labels(&self, layout: &[Field]) -> Vec<String> {
+        layout
+            .iter()
+            .map(|field| {
+                if field.signed {
+                    let value = bits::get_signed(self.state, field.offset, field.width);
+                    return format!("{}={value}", field.name);
+                }
+                let value = bits::get(self.state, field.offset, field.width);
+                if field.width > 8 {
+                    format!("{}={value:#x}", field.name)
+                } else {
+                    format!("{}={value}", field.name)
+                }
+            })
+            .collect()
+    }
+}
+
+fn field_index(layout: &[Field], bit: u32) -> Option<usize> {
+    layout
+        .iter()
+        .position(|field| (field.offset..field.offset + field.width).contains(&bit))
+}
@@ -0,0 +1,220 @@
+use crate::bits;
+
+/// One field of a packed state: where it lives and how wide it is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Field {
+    pub name: &'static str,
+    pub offset: u32,
+    pub width: u32,
+    /// Whether the field holds a two's complement value.
+    pub signed: bool,
+}
+
+/// A struct packed into a `u64`, usually generated by [`bit_layout!`](crate::bit_layout).
+pub trait BitLayout: Sized {
+    /// The fields in declaration order, starting at bit 0.
+    const FIELDS: &'static [Field];
+
+    fn from_u64(state: u64) -> Self;
+    fn to_u64(&self) -> u64;
+}
+
+/// A type that can be stored in a bit field of a packed state.
+pub trait BitField: Copy + PartialEq {
+    const BITS: u32;
+    /// Whether values are stored as two's complement.
+    const SIGNED: bool;
+
+    /// Converts the raw bits of a `width` bit field into a value.
+    fn from_bits(bits: u64, width: u32) -> Self;
+    fn to_bits(self) -> u64;
+}
+
+macro_rules! impl_unsigned_bit_field {
+    ($($ty:ty),*) => {
+        $(
+            impl BitField for $ty {
+                const BITS: u32 = <$ty>::BITS;
+                const SIGNED: bool = false;
+
+                fn from_bits(bits: u64, _width: u32) -> Self {
+                    bits as $ty
+                }
+
+                fn to_bits(self) -> u64 {
+                    self as u64
+                }
+            }
+        )*
+    };
+}
+
+impl_unsigned_bit_field!(u8, u16, u32, u64);
+
+macro_rules! impl_signed_bit_field {
+    ($($ty:ty),*) => {
+        $(
+            impl BitField for $ty {
+                const BITS: u32 = <$ty>::BITS;
+                const SIGNED: bool = true;
+
+                fn from_bits(bits: u64, width: u32) -> Self {
+                    bits::sign_extend(bits, width) as $ty
+                }
+
+                fn to_bits(self) -> u64 {
+                    self as i64 as u64
+                }
+            }
+        )*
+    };
+}
+
+impl_signed_bit_field!(i8, i16, i32, i64);
+
+impl BitField for bool {
+    const BITS: u32 = 1;
+    const SIGNED: bool = false;
+
+    fn from_bits(bits: u64, _width: u32) -> Self {
+        bits != 0
+    }
+
+    fn to_bits(self) -> u64 {
+        self as u64
+    }
+}
+
+/// Lays fields out back to back from bit 0. Used by [`bit_layout!`](crate::bit_layout).
+pub const fn fields<const N: usize>(
+    names: [&'static str; N],
+    widths: [u32; N],
+    signed: [bool; N],
+) -> [Field; N] {
+    let mut fields = [Field {
+        name: "",
+        offset: 0,
+        width: 0,
+        signed: false,
+    }; N];
+    let mut offset = 0;
+    let mut i = 0;
+    while i < N {
+        fields[i] = Field {
+            name: names[i],
+            offset,
+            width: widths[i],
+            signed: signed[i],
+        };
+        offset += widths[i];
+        i += 1;
+    }
+    fields
+}
+
+pub const fn total_width(fields: &[Field]) -> u32 {
+    let mut total = 0;
+    let mut i = 0;
+    while i < fields.len() {
+        total += fields[i].width;
+        i += 1;
+    }
+    total
+}
+
+/// Declares a struct whose fields are packed into a `u64` with the given bit
+/// widths, implementing [`BitLayout`] for it.
+///
+/// Fields are laid out in declaration order starting at bit 0. Signed fields
+/// are stored as two's complement. A layout wider than 64 bits, or a field
+/// wider than its type, fails to compile.
+///
+/// ```
+/// use bitwise_games::bit_layout;
+/// use bitwise_games::layout::BitLayout;
+///
+/// bit_layout! {
+///     struct Snake {
+///         head: u8 = 6,
+///         direction: u8 = 2,
+///         alive: bool = 1,
+///     }
+/// }
+///
+/// let snake = Snake { head: 42, direction: 3, alive: true };
+/// assert_eq!(snake.to_u64(), 42 | 3 << 6 | 1 << 8);
+/// assert_eq!(Snake::from_u64(snake.to_u64()).head, 42);
+/// ```
+#[macro_export]
+macro_rules! bit_layout {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $field_vis:vis $field:ident : $ty:ty = $width:expr
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        const _: () = {
+            let fields = <$name as $crate::layout::BitLayout>::FIELDS;
+            assert!(
+                $crate::layout::total_width(fields) <= 64,
+                concat!("fields of ", stringify!($name), " do not fit in 64 bits")
+            );
+            $(
+                assert!(
+                    $width > 0 && $width <= <$ty as $crate::layout::BitField>::BITS,
+                    concat!("width of ", stringify!($name), "::", stringify!($field), " does not fit its type")
+                );
+            )*
+        };
+
+        impl $crate::layout::BitLayout for $name {
+            const FIELDS: &'static [$crate::layout::Field] = &$crate::layout::fields(
+                [$(stringify!($field)),*],
+                [$($width),*],
+                [$(<$ty as $crate::layout::BitField>::SIGNED),*],
+            );
+
+            fn from_u64(state: u64) -> Self {
+                let mut fields = Self::FIELDS.iter();
+                Self {
+                    $(
+                        $field: {
+                            let field = fields.next().unwrap();
+                            let bits = $crate::bits::get(state, field.offset, field.width);
+                            <$ty as $crate::layout::BitField>::from_bits(bits, field.width)
+                        },
+                    )*
+                }
+            }
+
+            fn to_u64(&self) -> u64 {
+                let mut fields = Self::FIELDS.iter();
+                let mut state = 0u64;
+                $(
+                    let field = fields.next().unwrap();
+                    let bits = $crate::layout::BitField::to_bits(self.$field);
+                    state = $crate::bits::set(state, field.offset, field.width, bits);
+                    debug_assert!(
+                        <$ty as $crate::layout::BitField>::from_bits(
+                            $crate::bits::get(state, field.offset, field.width),
+                            field.width,
+                        ) == self.$field,
+                        concat!(stringify!($name), "::", stringify!($field), " does not fit its width")
+                    );
+                )*
+                state
+            }
+        }
+    };
+}
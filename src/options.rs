@@ -0,0 +1,100 @@
+use std::path::PathBuf;
+
+/// Flags understood by the runner itself rather than by the game.
+#[derive(Debug)]
+pub struct Options {
+    /// `--record <path>`: write a replay of the session when it ends.
+    pub record: Option<PathBuf>,
+    /// `--replay <path>`: play back a replay instead of reading the keyboard.
+    pub replay: Option<PathBuf>,
+    /// `--gif <path>`: record the session as an animated GIF. With `--replay`
+    /// the replay is rendered straight to the GIF without opening a window.
+    pub gif: Option<PathBuf>,
+    /// `--wav <path>`: write the session's sound to a WAV file. With
+    /// `--replay` the replay is rendered straight to the file.
+    pub wav: Option<PathBuf>,
+    /// `--dump <path>`: stream every frame's draw commands to a file or pipe,
+    /// in the `command_list` frame format.
+    pub dump: Option<PathBuf>,
+    /// `--mute`: don't play sound.
+    pub mute: bool,
+    /// `--load <slot>`: start from a saved state.
+    pub load: Option<u8>,
+    /// `--save-dir <dir>`: where save slots live, `saves` by default.
+    pub save_dir: PathBuf,
+    /// `--screenshot-dir <dir>`: where screenshots and clips go, `screenshots` by default.
+    pub screenshot_dir: PathBuf,
+    /// `--terminal`: draw in the terminal instead of opening a window.
+    pub terminal: bool,
+    /// `--crisp`: rasterize at the size frames are shown at instead of
+    /// scaling up the game's own resolution.
+    pub crisp: bool,
+    /// `--debug`: enable pausing, single-stepping and speed control.
+    pub debug: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            record: None,
+            replay: None,
+            gif: None,
+            wav: None,
+            dump: None,
+            mute: false,
+            load: None,
+            save_dir: PathBuf::from("saves"),
+            screenshot_dir: PathBuf::from("screenshots"),
+            terminal: false,
+            crisp: false,
+            debug: false,
+        }
+    }
+}
+
+impl Options {
+    /// Splits runner flags out of `args`, returning them along with the
+    /// arguments left over for `Game::new`.
+    pub fn parse(args: Vec<String>) -> Result<(Self, Vec<String>), String> {
+        let mut options = Options::default();
+        let mut rest = Vec::with_capacity(args.len());
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--record" => options.record = Some(flag_value(&arg, args.next())?.into()),
+                "--replay" => options.replay = Some(flag_value(&arg, args.next())?.into()),
+                "--gif" => options.gif = Some(flag_value(&arg, args.next())?.into()),
+                "--wav" => options.wav = Some(flag_value(&arg, args.next())?.into()),
+                "--dump" => options.dump = Some(flag_value(&arg, args.next())?.into()),
+                "--mute" => options.mute = true,
+                "--load" => {
+                    let slot = flag_value(&arg, args.next())?;
+                    let slot = slot
+                        .parse()
+                        .map_err(|_| format!("{arg} expects a slot number, got {slot}"))?;
+                    options.load = Some(slot);
+                }
+                "--debug" => options.debug = true,
+                "--terminal" => options.terminal = true,
+                "--crisp" => options.crisp = true,
+                "--save-dir" => options.save_dir = flag_value(&arg, args.next())?.into(),
+                "--screenshot-dir" => {
+                    options.screenshot_dir = flag_value(&arg, args.next())?.into();
+                }
+                _ => rest.push(arg),
+            }
+        }
+
+        // A replay starts from its own initial state
+        if options.replay.is_some() && options.load.is_some() {
+            return Err("--load can't be used with --replay".to_string());
+        }
+
+        Ok((options, rest))
+    }
+}
+
+fn flag_value(flag: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("{flag} expects a value"))
+}
@@ -1 +1,254 @@
+//! Sound output: the sounds games emit, a synthesizer that mixes them, and
+//! WAV encoding for writing the result to disk.
 
+use crate::game::Game;
+use crate::replay::Replay;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+pub const SAMPLE_RATE: u32 = 22_050;
+
+/// Frequency of pitch 0, the C two octaves below middle C.
+const BASE_FREQUENCY: f32 = 65.406;
+
+/// Sounds fade in and out over this long to avoid clicks.
+const FADE: Duration = Duration::from_millis(4);
+
+/// Every voice is scaled down so a few can play at once before clipping.
+const VOICE_GAIN: f32 = 0.3;
+
+/// Oscillator shapes, as on PICO-8.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Triangle,
+    Noise,
+}
+
+/// A tone that starts on the frame it is emitted for. Sounds overlap freely.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sound {
+    pub waveform: Waveform,
+    /// Semitones above C2 (65.4 Hz), so 24 is middle C and 12 steps is an octave.
+    pub pitch: u8,
+    pub duration: Duration,
+    /// From 0.0 for silence to 1.0.
+    pub volume: f32,
+}
+
+impl Sound {
+    pub fn frequency(&self) -> f32 {
+        BASE_FREQUENCY * 2f32.powf(f32::from(self.pitch) / 12.0)
+    }
+}
+
+struct Voice {
+    waveform: Waveform,
+    /// Oscillator cycles per sample.
+    step: f32,
+    phase: f32,
+    volume: f32,
+    position: u32,
+    length: u32,
+    noise: f32,
+}
+
+/// Mixes sounds into mono 16-bit samples, one frame's worth at a time.
+pub struct Synth {
+    sample_rate: u32,
+    fps: u64,
+    frame: u64,
+    voices: Vec<Voice>,
+    random: u32,
+}
+
+impl Synth {
+    pub fn new(sample_rate: u32, fps: u32) -> Self {
+        Self {
+            sample_rate,
+            fps: u64::from(fps.max(1)),
+            frame: 0,
+            voices: Vec::new(),
+            random: 0x2545_f491,
+        }
+    }
+
+    pub fn play(&mut self, sound: &Sound) {
+        let length = (sound.duration.as_secs_f32() * self.sample_rate as f32) as u32;
+        if length == 0 || sound.volume <= 0.0 {
+            return;
+        }
+        self.voices.push(Voice {
+            waveform: sound.waveform,
+            step: sound.frequency() / self.sample_rate as f32,
+            phase: 0.0,
+            volume: sound.volume.min(1.0),
+            position: 0,
+            length,
+            noise: 0.0,
+        });
+    }
+
+    /// Renders the samples for the next frame. Frames alternate between
+    /// lengths when the sample rate isn't a multiple of the frame rate, so
+    /// the audio keeps pace with the frames.
+    pub fn frame(&mut self) -> Vec<i16> {
+        let rate = u64::from(self.sample_rate);
+        let start = self.frame * rate / self.fps;
+        self.frame += 1;
+        let end = self.frame * rate / self.fps;
+        self.render((end - start) as usize)
+    }
+
+    pub fn render(&mut self, count: usize) -> Vec<i16> {
+        let fade = ((FADE.as_secs_f32() * self.sample_rate as f32) as u32).max(1);
+        let mut samples = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut mix = 0.0;
+            for voice in &mut self.voices {
+                if voice.position >= voice.length {
+                    continue;
+                }
+                let wave = match voice.waveform {
+                    Waveform::Square => {
+                        if voice.phase < 0.5 {
+                            1.0
+                        } else {
+                            -1.0
+                        }
+                    }
+                    Waveform::Triangle => 1.0 - 4.0 * (voice.phase - 0.5).abs(),
+                    Waveform::Noise => voice.noise,
+                };
+                let envelope = fade
+                    .min(voice.position + 1)
+                    .min(voice.length - voice.position) as f32
+                    / fade as f32;
+                mix += wave * envelope * voice.volume * VOICE_GAIN;
+
+                voice.position += 1;
+                voice.phase += voice.step;
+                if voice.phase >= 1.0 {
+                    voice.phase -= voice.phase.floor();
+                    // Noise picks a new level every cycle, so its pitch still matters
+                    self.random ^= self.random << 13;
+                    self.random ^= self.random >> 17;
+                    self.random ^= self.random << 5;
+                    voice.noise = self.random as f32 / u32::MAX as f32 * 2.0 - 1.0;
+                }
+            }
+            samples.push((mix.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16);
+        }
+        self.voices.retain(|voice| voice.position < voice.length);
+        samples
+    }
+}
+
+/// Synthesizes the sounds of a replay without playing them.
+pub fn render_replay<T: Game>(replay: &Replay) -> Vec<i16> {
+    let mut synth = Synth::new(SAMPLE_RATE, T::FPS as u32);
+    let mut samples = Vec::new();
+    let mut state = replay.initial_state;
+    for &input in &replay.inputs {
+        let next = T::step(state, input);
+        for sound in T::sounds(state, next) {
+            synth.play(&sound);
+        }
+        state = next;
+        samples.extend(synth.frame());
+    }
+    samples
+}
+
+/// Encodes mono 16-bit samples as a WAV file.
+pub fn encode_wav(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut wav = Vec::with_capacity(44 + samples.len() * 2);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, one channel
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+pub fn write_wav(path: impl AsRef<Path>, samples: &[i16], sample_rate: u32) -> io::Result<()> {
+    fs::write(path, encode_wav(samples, sample_rate))
+}
+
+/// Plays samples through ALSA's `aplay` where it is installed. Elsewhere,
+/// including on every platform but Linux, `start` says so on stderr and
+/// returns `None`.
+///
+/// Samples are handed to a writer thread so a slow audio device never holds
+/// up a frame; if it falls behind, frames of audio are dropped instead.
+/// Dropping a `Playback` closes `aplay`'s input and waits for it to exit.
+pub(crate) struct Playback {
+    sender: Option<SyncSender<Vec<i16>>>,
+    writer: Option<JoinHandle<()>>,
+    child: Child,
+}
+
+impl Playback {
+    pub(crate) fn start(sample_rate: u32) -> Option<Self> {
+        let mut child = Command::new("aplay")
+            .args(["-q", "-t", "raw", "-f", "S16_LE", "-c", "1"])
+            .arg(format!("-r{sample_rate}"))
+            .arg("--buffer-time=100000")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .inspect_err(|err| eprintln!("No sound: could not start aplay: {err}"))
+            .ok()?;
+        let mut stdin = child.stdin.take()?;
+
+        let (sender, receiver) = mpsc::sync_channel::<Vec<i16>>(8);
+        let writer = thread::spawn(move || {
+            for samples in receiver {
+                let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+                if stdin.write_all(&bytes).is_err() {
+                    break;
+                }
+            }
+        });
+        Some(Self {
+            sender: Some(sender),
+            writer: Some(writer),
+            child,
+        })
+    }
+
+    pub(crate) fn write(&self, samples: Vec<i16>) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.try_send(samples);
+        }
+    }
+}
+
+impl Drop for Playback {
+    fn drop(&mut self) {
+        // Hanging up ends the writer thread, which closes aplay's stdin
+        self.sender = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+        let _ = self.child.wait();
+    }
+}
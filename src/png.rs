@@ -0,0 +1,190 @@
+//! A minimal PNG encoder.
+//!
+//! Images are written as 8-bit RGB with the pixel data in uncompressed
+//! deflate blocks. The files are larger than they need to be but every
+//! decoder reads them, and the small frames this crate draws stay small.
+//!
+//! [`decode`] reads those files back, e.g. reference images for snapshot
+//! tests. It does not inflate compressed data, so it rejects PNGs written by
+//! other encoders.
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Deflate's stored blocks hold at most this many bytes.
+const MAX_STORED_BLOCK: usize = 0xffff;
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+/// Encodes `0x00RRGGBB` pixels, row by row, as a PNG file.
+pub fn encode(pixels: &[u32], width: u32, height: u32) -> Vec<u8> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGB, deflate, adaptive filtering, no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    // Every row starts with its filter type, 0 for none
+    let mut raw = Vec::with_capacity(height as usize * (1 + width as usize * 3));
+    for row in pixels.chunks(width.max(1) as usize).take(height as usize) {
+        raw.push(0);
+        for &pixel in row {
+            raw.extend_from_slice(&[(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]);
+        }
+    }
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Decodes a PNG written by [`encode`] into pixels, width and height. Returns
+/// `None` for anything else, including PNGs that use compression.
+pub fn decode(png: &[u8]) -> Option<(Vec<u32>, u32, u32)> {
+    let mut rest = png.strip_prefix(&SIGNATURE)?;
+    let mut header = None;
+    let mut zlib = Vec::new();
+    loop {
+        let len = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+        let end = len.checked_add(12)?;
+        let chunk = rest.get(4..end - 4)?;
+        let crc = u32::from_be_bytes(rest.get(end - 4..end)?.try_into().ok()?);
+        if crc32(chunk) != crc {
+            return None;
+        }
+        rest = &rest[end..];
+        let (kind, data) = chunk.split_at(4);
+        match kind {
+            b"IHDR" => header = Some(data),
+            b"IDAT" => zlib.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+    }
+
+    let header = header?;
+    let width = u32::from_be_bytes(header.get(..4)?.try_into().ok()?);
+    let height = u32::from_be_bytes(header.get(4..8)?.try_into().ok()?);
+    if header.get(8..)? != [8, 2, 0, 0, 0] {
+        return None;
+    }
+
+    let raw = zlib_unstored(&zlib)?;
+    // The header's sizes come from the file, so their product may overflow
+    let stride = (width as usize).checked_mul(3)?.checked_add(1)?;
+    if raw.len() != stride.checked_mul(height as usize)? {
+        return None;
+    }
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+    for row in raw.chunks(stride) {
+        // Only the unfiltered rows `encode` writes
+        if row[0] != 0 {
+            return None;
+        }
+        pixels.extend(
+            row[1..].chunks(3).map(|rgb| {
+                (u32::from(rgb[0]) << 16) | (u32::from(rgb[1]) << 8) | u32::from(rgb[2])
+            }),
+        );
+    }
+    Some((pixels, width, height))
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wraps `data` in a zlib stream of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(MAX_STORED_BLOCK).max(1);
+    let mut out = Vec::with_capacity(data.len() + blocks * 5 + 6);
+    // Deflate with a 32K window, no preset dictionary
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+        out.push(u8::from(last));
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// The inverse of [`zlib_stored`].
+fn zlib_unstored(zlib: &[u8]) -> Option<Vec<u8>> {
+    let mut rest = zlib.get(2..)?;
+    let mut data = Vec::new();
+    loop {
+        let (&block, header) = rest.split_first()?;
+        // Anything but a stored block would need inflating
+        if block & !1 != 0 {
+            return None;
+        }
+        let len = u16::from_le_bytes(header.get(..2)?.try_into().ok()?);
+        let nlen = u16::from_le_bytes(header.get(2..4)?.try_into().ok()?);
+        if len != !nlen {
+            return None;
+        }
+        data.extend_from_slice(header.get(4..4 + len as usize)?);
+        rest = &header[4 + len as usize..];
+        if block == 1 {
+            break;
+        }
+    }
+    let adler = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?);
+    (adler == adler32(&data)).then_some(data)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let crc = data.iter().fold(0xffff_ffff, |crc, &byte| {
+        CRC_TABLE[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8)
+    });
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 bytes is the most that can be summed before `b` could overflow
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
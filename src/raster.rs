@@ -0,0 +1,370 @@
+//! Shape rasterization shared by the framebuffer types.
+
+use crate::draw_command::{
+    Circle, Clip, Color, DrawCommand, Line, Polygon, Rectangle, Sprite, Style, Text, Triangle,
+};
+use crate::font;
+use std::ops::Range;
+
+/// The drawable area as half-open ranges. Shape maths is done in `i64` so
+/// that no coordinate or extent in a command can overflow.
+#[derive(Clone, Copy)]
+pub(crate) struct Bounds {
+    left: i64,
+    top: i64,
+    right: i64,
+    bottom: i64,
+}
+
+impl Bounds {
+    /// A `width` x `height` target, narrowed to `clip` if there is one.
+    pub(crate) fn new(width: u32, height: u32, clip: Option<Clip>) -> Self {
+        let mut bounds = Bounds {
+            left: 0,
+            top: 0,
+            right: width.into(),
+            bottom: height.into(),
+        };
+        if let Some(clip) = clip {
+            bounds.left = bounds.left.max(clip.x.into());
+            bounds.top = bounds.top.max(clip.y.into());
+            bounds.right = bounds.right.min(i64::from(clip.x) + i64::from(clip.width));
+            bounds.bottom = bounds
+                .bottom
+                .min(i64::from(clip.y) + i64::from(clip.height));
+        }
+        bounds
+    }
+
+    /// The rows and columns of `sprite`'s pixels that land inside the bounds,
+    /// so that a large sprite mostly off screen costs only its visible part.
+    pub(crate) fn sprite_cells(&self, sprite: &Sprite) -> (Range<u32>, Range<u32>) {
+        let scale = i64::from(sprite.scale.max(1));
+        let rows = visible_cells(sprite.y.into(), scale, sprite.height, self.top, self.bottom);
+        let columns = visible_cells(sprite.x.into(), scale, sprite.width, self.left, self.right);
+        (rows, columns)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.left >= self.right || self.top >= self.bottom
+    }
+
+    fn overlaps(&self, left: i64, top: i64, right: i64, bottom: i64) -> bool {
+        left < self.right && right > self.left && top < self.bottom && bottom > self.top
+    }
+}
+
+/// Turns shape commands into the pixels they cover, clipped to `bounds`, and
+/// hands each pixel with its colour to `plot`.
+pub(crate) struct Rasterizer<F> {
+    bounds: Bounds,
+    plot: F,
+}
+
+impl<F: FnMut(i64, i64, &Color)> Rasterizer<F> {
+    pub(crate) fn new(bounds: Bounds, plot: F) -> Self {
+        Self { bounds, plot }
+    }
+
+    /// Rasterizes one shape. State commands such as blend modes and clips
+    /// belong to the framebuffer and are ignored here.
+    pub(crate) fn draw(&mut self, command: &DrawCommand) {
+        match command {
+            DrawCommand::BlendMode(_) | DrawCommand::Clip(_) => {}
+            DrawCommand::Rectangle(rect) => self.draw_rectangle(rect),
+            DrawCommand::Line(line) => self.draw_line(line),
+            DrawCommand::Circle(circle) => self.draw_circle(circle),
+            DrawCommand::Triangle(triangle) => self.draw_triangle(triangle),
+            DrawCommand::Polygon(polygon) => self.draw_polygon(polygon),
+            DrawCommand::Text(text) => self.draw_text(text),
+            DrawCommand::Sprite(sprite) => self.draw_sprite(sprite),
+        }
+    }
+
+    fn draw_rectangle(&mut self, rect: &Rectangle) {
+        let left = i64::from(rect.x);
+        let top = i64::from(rect.y);
+        let right = left + i64::from(rect.width);
+        let bottom = top + i64::from(rect.height);
+
+        match rect.style {
+            Style::Fill => self.fill_rect(left, top, right, bottom, &rect.color),
+            Style::Stroke if rect.width > 0 && rect.height > 0 => {
+                self.fill_rect(left, top, right, top + 1, &rect.color);
+                self.fill_rect(left, bottom - 1, right, bottom, &rect.color);
+                self.fill_rect(left, top, left + 1, bottom, &rect.color);
+                self.fill_rect(right - 1, top, right, bottom, &rect.color);
+            }
+            Style::Stroke => {}
+        }
+    }
+
+    fn fill_rect(&mut self, left: i64, top: i64, right: i64, bottom: i64, color: &Color) {
+        let bounds = self.bounds;
+        for y in top.max(bounds.top)..bottom.min(bounds.bottom) {
+            self.fill_span(left, right - 1, y, color);
+        }
+    }
+
+    fn draw_line(&mut self, line: &Line) {
+        let from = (i64::from(line.x1), i64::from(line.y1));
+        let to = (i64::from(line.x2), i64::from(line.y2));
+        self.plot_line(from, to, &line.color);
+    }
+
+    fn plot_line(&mut self, from: (i64, i64), to: (i64, i64), color: &Color) {
+        let Some(((mut x0, mut y0), (x1, y1))) = clip_line(from, to, self.bounds) else {
+            return;
+        };
+
+        // Bresenham's line algorithm
+        let dx = (x1 - x0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let dy = -(y1 - y0).abs();
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+
+        loop {
+            (self.plot)(x0, y0, color);
+            if x0 == x1 && y0 == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x0 += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y0 += sy;
+            }
+        }
+    }
+
+    /// Rasterizes row by row so that only visible rows cost anything.
+    ///
+    /// A pixel is inside when `x² + y² <= r² + r`, which matches the midpoint
+    /// circle algorithm. The outline is every inside pixel with a horizontal
+    /// or vertical neighbour outside.
+    fn draw_circle(&mut self, circle: &Circle) {
+        let bounds = self.bounds;
+        let x_center = i64::from(circle.x);
+        let y_center = i64::from(circle.y);
+        let radius = i64::from(circle.radius);
+        if !bounds.overlaps(
+            x_center - radius,
+            y_center - radius,
+            x_center + radius + 1,
+            y_center + radius + 1,
+        ) {
+            return;
+        }
+
+        let half_width = |dy: i64| -> i64 {
+            let (r, dy) = (radius as u64, dy.unsigned_abs());
+            if dy > r {
+                -1
+            } else {
+                (r * r + r - dy * dy).isqrt() as i64
+            }
+        };
+
+        let top = (y_center - radius).max(bounds.top);
+        let bottom = (y_center + radius + 1).min(bounds.bottom);
+        for y in top..bottom {
+            let dy = y - y_center;
+            let outer = half_width(dy);
+            match circle.style {
+                Style::Fill => self.fill_span(x_center - outer, x_center + outer, y, &circle.color),
+                Style::Stroke => {
+                    let inner = half_width(dy.abs() + 1).min(outer - 1);
+                    self.fill_span(x_center - outer, x_center - inner - 1, y, &circle.color);
+                    self.fill_span(x_center + inner + 1, x_center + outer, y, &circle.color);
+                }
+            }
+        }
+    }
+
+    fn draw_triangle(&mut self, triangle: &Triangle) {
+        let points = [
+            (triangle.x1, triangle.y1),
+            (triangle.x2, triangle.y2),
+            (triangle.x3, triangle.y3),
+        ];
+        if triangle.style == Style::Fill {
+            self.fill_polygon(&points, &triangle.color);
+        }
+        self.stroke_polygon(&points, &triangle.color);
+    }
+
+    fn draw_polygon(&mut self, polygon: &Polygon) {
+        if polygon.style == Style::Fill {
+            self.fill_polygon(&polygon.points, &polygon.color);
+        }
+        self.stroke_polygon(&polygon.points, &polygon.color);
+    }
+
+    fn stroke_polygon(&mut self, points: &[(i32, i32)], color: &Color) {
+        for (i, &(x1, y1)) in points.iter().enumerate() {
+            let (x2, y2) = points[(i + 1) % points.len()];
+            let from = (i64::from(x1), i64::from(y1));
+            let to = (i64::from(x2), i64::from(y2));
+            self.plot_line(from, to, color);
+        }
+    }
+
+    /// Scanline fill using the even-odd rule, sampling at pixel centres.
+    ///
+    /// Each row is filled between pairs of edge crossings. Edges are treated as
+    /// half open so a vertex shared by two edges is only counted once; the
+    /// outline drawn on top covers the pixels this leaves out.
+    fn fill_polygon(&mut self, points: &[(i32, i32)], color: &Color) {
+        let bounds = self.bounds;
+        let Some(min_y) = points.iter().map(|&(_, y)| i64::from(y)).min() else {
+            return;
+        };
+        let max_y = points
+            .iter()
+            .map(|&(_, y)| i64::from(y))
+            .max()
+            .unwrap_or(min_y);
+
+        let mut crossings = Vec::new();
+        for y in min_y.max(bounds.top)..=max_y.min(bounds.bottom - 1) {
+            crossings.clear();
+            for (i, &(x0, y0)) in points.iter().enumerate() {
+                let (x1, y1) = points[(i + 1) % points.len()];
+                let (x0, y0, x1, y1) = (f64::from(x0), i64::from(y0), f64::from(x1), i64::from(y1));
+                if (y0 <= y && y < y1) || (y1 <= y && y < y0) {
+                    let t = (y - y0) as f64 / (y1 - y0) as f64;
+                    crossings.push(x0 + t * (x1 - x0));
+                }
+            }
+            crossings.sort_by(f64::total_cmp);
+            for pair in crossings.chunks_exact(2) {
+                self.fill_span(pair[0].ceil() as i64, pair[1].floor() as i64, y, color);
+            }
+        }
+    }
+
+    /// Fills `x_start..=x_end` on row `y`, clipped to the drawable area.
+    fn fill_span(&mut self, x_start: i64, x_end: i64, y: i64, color: &Color) {
+        let bounds = self.bounds;
+        if y < bounds.top || y >= bounds.bottom {
+            return;
+        }
+        for x in x_start.max(bounds.left)..=x_end.min(bounds.right - 1) {
+            (self.plot)(x, y, color);
+        }
+    }
+
+    fn draw_text(&mut self, text: &Text) {
+        let bounds = self.bounds;
+        let scale = i64::from(text.scale.max(1));
+        let glyph_width = i64::from(font::GLYPH_WIDTH) * scale;
+        let glyph_height = i64::from(font::GLYPH_HEIGHT) * scale;
+
+        for (row, line) in text.text.lines().enumerate() {
+            let y = i64::from(text.y) + row as i64 * i64::from(font::LINE_HEIGHT) * scale;
+            for (column, c) in line.chars().enumerate() {
+                let x = i64::from(text.x) + column as i64 * i64::from(font::ADVANCE) * scale;
+                if !bounds.overlaps(x, y, x + glyph_width, y + glyph_height) {
+                    continue;
+                }
+                for (dy, bits) in (0..).zip(font::glyph(c)) {
+                    for dx in 0..i64::from(font::GLYPH_WIDTH) {
+                        if bits & (0b100 >> dx) != 0 {
+                            let left = x + dx * scale;
+                            let top = y + dy * scale;
+                            self.fill_rect(left, top, left + scale, top + scale, &text.color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw_sprite(&mut self, sprite: &Sprite) {
+        let scale = i64::from(sprite.scale.max(1));
+        let left = i64::from(sprite.x);
+        let top = i64::from(sprite.y);
+        // Empty when the sprite is entirely outside the bounds
+        let (rows, columns) = self.bounds.sprite_cells(sprite);
+        for row in rows {
+            let source_y = if sprite.flip_y {
+                sprite.height - 1 - row
+            } else {
+                row
+            };
+            let y = top + i64::from(row) * scale;
+            for column in columns.clone() {
+                let source_x = if sprite.flip_x {
+                    sprite.width - 1 - column
+                } else {
+                    column
+                };
+                let value = sprite.value(source_x, source_y);
+                let Some(color) = usize::from(value)
+                    .checked_sub(1)
+                    .and_then(|i| sprite.colors.get(i))
+                else {
+                    continue;
+                };
+                let x = left + i64::from(column) * scale;
+                self.fill_rect(x, y, x + scale, y + scale, color);
+            }
+        }
+    }
+}
+
+/// The cells of a row or column of `count` cells, each `scale` pixels wide and
+/// starting at `start`, that overlap the pixels from `low` to `high`.
+fn visible_cells(start: i64, scale: i64, count: u32, low: i64, high: i64) -> Range<u32> {
+    let first = (low - start).div_euclid(scale).clamp(0, count.into());
+    let end = (high - start + scale - 1)
+        .div_euclid(scale)
+        .clamp(first, count.into());
+    first as u32..end as u32
+}
+
+/// Liang-Barsky clipping of a segment to the centres of the pixels in `bounds`.
+///
+/// Segments already inside come back unchanged, so on-screen lines rasterize
+/// exactly as before; clipped endpoints are rounded to the nearest pixel.
+fn clip_line(from: (i64, i64), to: (i64, i64), bounds: Bounds) -> Option<((i64, i64), (i64, i64))> {
+    if bounds.is_empty() {
+        return None;
+    }
+    let (x0, y0) = (from.0 as f64, from.1 as f64);
+    let (dx, dy) = ((to.0 - from.0) as f64, (to.1 - from.1) as f64);
+    let (left, right) = (bounds.left as f64, (bounds.right - 1) as f64);
+    let (top, bottom) = (bounds.top as f64, (bounds.bottom - 1) as f64);
+
+    let mut t0 = 0.0f64;
+    let mut t1 = 1.0f64;
+    for (p, q) in [
+        (-dx, x0 - left),
+        (dx, right - x0),
+        (-dy, y0 - top),
+        (dy, bottom - y0),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+
+    let point = |t: f64| ((x0 + t * dx).round() as i64, (y0 + t * dy).round() as i64);
+    Some((point(t0), point(t1)))
+}
@@ -0,0 +1,193 @@
+use crate::game::Game;
+use crate::input::Input;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"BWRP";
+const VERSION: u8 = 1;
+
+/// Longest replay `decode` accepts, a week of play at 60 FPS. Run lengths come
+/// from the file, so without a limit a few corrupt bytes could ask for any
+/// number of frames.
+pub const MAX_FRAMES: u64 = 60 * 60 * 60 * 24 * 7;
+
+/// A recorded session: the initial state plus the input of every frame.
+///
+/// Because games are pure functions of their state and input, this is enough
+/// to reproduce the session exactly. The final state is stored alongside so
+/// playback can be verified.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    pub initial_state: u64,
+    pub final_state: u64,
+    pub inputs: Vec<Input>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+    /// The runs add up to more than [`MAX_FRAMES`] frames.
+    TooLong,
+    Desync {
+        expected: u64,
+        actual: u64,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "replay io error: {err}"),
+            ReplayError::BadMagic => write!(f, "not a replay file"),
+            ReplayError::UnsupportedVersion(version) => {
+                write!(f, "unsupported replay version {version}")
+            }
+            ReplayError::Truncated => write!(f, "replay file is truncated"),
+            ReplayError::TooLong => write!(f, "replay is longer than {MAX_FRAMES} frames"),
+            ReplayError::Desync { expected, actual } => write!(
+                f,
+                "replay desynced: expected final state {expected:#018x}, got {actual:#018x}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+impl Replay {
+    pub fn new(initial_state: u64) -> Self {
+        Self {
+            initial_state,
+            final_state: initial_state,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Records one frame: the input fed to the game and the state it produced.
+    pub fn push(&mut self, input: Input, state: u64) {
+        self.inputs.push(input);
+        self.final_state = state;
+    }
+
+    /// Drops the last frame after rewinding, leaving `state` as the final state.
+    pub fn pop(&mut self, state: u64) {
+        self.inputs.pop();
+        self.final_state = state;
+    }
+
+    /// Plays the inputs back through `T::step` and checks the final state.
+    pub fn verify<T: Game>(&self) -> Result<(), ReplayError> {
+        let actual = self
+            .inputs
+            .iter()
+            .fold(self.initial_state, |state, &input| T::step(state, input));
+        if actual == self.final_state {
+            Ok(())
+        } else {
+            Err(ReplayError::Desync {
+                expected: self.final_state,
+                actual,
+            })
+        }
+    }
+
+    /// Encodes the replay as a header followed by run-length encoded inputs.
+    ///
+    /// Layout: magic, version byte, initial and final state (little endian),
+    /// then `(run length varint, input bits)` pairs until the end.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(21);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.initial_state.to_le_bytes());
+        bytes.extend_from_slice(&self.final_state.to_le_bytes());
+
+        let mut inputs = self.inputs.iter().peekable();
+        while let Some(&input) = inputs.next() {
+            let mut run = 1u64;
+            while inputs.next_if_eq(&&input).is_some() {
+                run += 1;
+            }
+            write_varint(&mut bytes, run);
+            bytes.push(input.bits());
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let (magic, rest) = bytes.split_at_checked(4).ok_or(ReplayError::Truncated)?;
+        if magic != MAGIC {
+            return Err(ReplayError::BadMagic);
+        }
+        let (&version, rest) = rest.split_first().ok_or(ReplayError::Truncated)?;
+        if version != VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        let (initial_state, rest) = read_u64(rest)?;
+        let (final_state, mut rest) = read_u64(rest)?;
+
+        let mut inputs = Vec::new();
+        while !rest.is_empty() {
+            let run;
+            (run, rest) = read_varint(rest)?;
+            let (&bits, tail) = rest.split_first().ok_or(ReplayError::Truncated)?;
+            rest = tail;
+            if run > MAX_FRAMES - inputs.len() as u64 {
+                return Err(ReplayError::TooLong);
+            }
+            inputs.extend(std::iter::repeat_n(Input::from_bits(bits), run as usize));
+        }
+
+        Ok(Self {
+            initial_state,
+            final_state,
+            inputs,
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        fs::write(path, self.encode())?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Self::decode(&fs::read(path)?)
+    }
+}
+
+fn read_u64(bytes: &[u8]) -> Result<(u64, &[u8]), ReplayError> {
+    let (head, rest) = bytes
+        .split_first_chunk::<8>()
+        .ok_or(ReplayError::Truncated)?;
+    Ok((u64::from_le_bytes(*head), rest))
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8]) -> Result<(u64, &[u8]), ReplayError> {
+    let mut value = 0u64;
+    for (i, &byte) in bytes.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, &bytes[i + 1..]));
+        }
+    }
+    Err(ReplayError::Truncated)
+}
@@ -0,0 +1,51 @@
+use std::collections::VecDeque;
+
+/// A ring buffer of past states for stepping backwards through time. The
+/// oldest states are dropped once full.
+pub struct RewindBuffer {
+    entries: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl RewindBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, state: u64) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(state);
+    }
+
+    pub fn pop(&mut self) -> Option<u64> {
+        self.entries.pop_back()
+    }
+
+    pub fn last(&self) -> Option<u64> {
+        self.entries.back().copied()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
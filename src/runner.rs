@@ -0,0 +1,253 @@
+//! Plumbing shared by the window and terminal runners.
+
+use crate::command_list;
+use crate::draw_command::DrawCommand;
+use crate::frame_buffer::FrameBuffer;
+use crate::game::Game;
+use crate::gif::Gif;
+use crate::options::Options;
+use crate::output::{self, Playback, SAMPLE_RATE, Sound, Synth};
+use crate::replay::Replay;
+use crate::save::{self, SaveSlots};
+use crate::scale;
+use crate::session::Session;
+use crate::svg;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub(crate) fn load_replay(options: &Options) -> Option<Replay> {
+    options
+        .replay
+        .as_ref()
+        .map(|path| Replay::load(path).unwrap_or_else(|e| panic!("{e}")))
+}
+
+/// Handles `--replay` with `--gif` or `--wav`, which need no window or
+/// terminal. Returns whether there is nothing left to do.
+pub(crate) fn render_replay<T: Game>(options: &Options, replay: Option<&Replay>) -> bool {
+    let Some(replay) = replay else {
+        return false;
+    };
+    if let Some(path) = &options.gif {
+        Gif::from_replay::<T>(replay)
+            .save(path)
+            .unwrap_or_else(|e| panic!("{e}"));
+    }
+    if let Some(path) = &options.wav {
+        let samples = output::render_replay::<T>(replay);
+        output::write_wav(path, &samples, SAMPLE_RATE).unwrap_or_else(|e| panic!("{e}"));
+    }
+    options.gif.is_some() || options.wav.is_some()
+}
+
+/// Starts from the replay's initial state, the `--load` slot or a new game.
+pub(crate) fn start_session<T: Game>(
+    args: Vec<String>,
+    replay: Option<&Replay>,
+    load: Option<u8>,
+    slots: &SaveSlots,
+) -> Session<T> {
+    match (replay, load) {
+        (Some(replay), _) => Session::with_state(replay.initial_state),
+        (None, Some(slot)) => {
+            Session::with_state(slots.load(slot).unwrap_or_else(|e| panic!("{e}")))
+        }
+        (None, None) => Session::new(args),
+    }
+}
+
+/// Sound for a running session, played live unless `--mute` and collected
+/// for `--wav`.
+pub(crate) struct Audio {
+    synth: Synth,
+    playback: Option<Playback>,
+    wav: Option<Vec<i16>>,
+}
+
+impl Audio {
+    pub(crate) fn new<T: Game>(options: &Options) -> Self {
+        Self {
+            synth: Synth::new(SAMPLE_RATE, T::FPS as u32),
+            playback: if options.mute {
+                None
+            } else {
+                Playback::start(SAMPLE_RATE)
+            },
+            wav: options.wav.as_ref().map(|_| Vec::new()),
+        }
+    }
+
+    /// Starts `sounds` and produces one frame of audio. Called every frame,
+    /// including paused ones, so that sound keeps time with the picture.
+    pub(crate) fn frame(&mut self, sounds: &[Sound]) {
+        for sound in sounds {
+            self.synth.play(sound);
+        }
+        let samples = self.synth.frame();
+        if let Some(wav) = &mut self.wav {
+            wav.extend_from_slice(&samples);
+        }
+        if let Some(playback) = &self.playback {
+            playback.write(samples);
+        }
+    }
+}
+
+/// The `--dump` stream of draw commands, one frame per displayed frame.
+pub(crate) struct Dump {
+    writer: Option<BufWriter<File>>,
+}
+
+impl Dump {
+    pub(crate) fn new(options: &Options) -> Self {
+        Self {
+            writer: options
+                .dump
+                .as_ref()
+                .map(|path| BufWriter::new(File::create(path).unwrap_or_else(|e| panic!("{e}")))),
+        }
+    }
+
+    /// Writes a frame. After an error, such as the viewer on the other end of
+    /// a pipe closing, dumping stops and the game carries on.
+    pub(crate) fn frame(&mut self, commands: &[DrawCommand]) -> io::Result<()> {
+        let Some(writer) = &mut self.writer else {
+            return Ok(());
+        };
+        command_list::write_frame(writer, commands).inspect_err(|_| self.writer = None)
+    }
+}
+
+/// Writes out the `--record` replay, the `--wav` audio and any clip still
+/// being recorded.
+pub(crate) fn finish<T: Game>(
+    options: &Options,
+    recording: Option<Replay>,
+    clip: Option<Gif>,
+    audio: Audio,
+) {
+    if let (Some(path), Some(recording)) = (&options.record, &recording) {
+        recording.save(path).unwrap_or_else(|e| panic!("{e}"));
+    }
+    if let (Some(path), Some(samples)) = (&options.wav, &audio.wav) {
+        output::write_wav(path, samples, SAMPLE_RATE).unwrap_or_else(|e| panic!("{e}"));
+    }
+    if let Some(gif) = clip {
+        let saved = match &options.gif {
+            Some(path) => gif.save(path).map(|()| path.clone()),
+            None => save_clip::<T>(&options.screenshot_dir, gif),
+        };
+        match saved {
+            Ok(path) => println!("Saved clip {}", path.display()),
+            Err(err) => println!("Could not save clip: {err}"),
+        }
+    }
+}
+
+pub(crate) fn replay_report(replay: &Replay, state: u64) -> String {
+    if state == replay.final_state {
+        format!("Replay finished: final state {state:#018x} matches")
+    } else {
+        format!(
+            "Replay desynced: expected final state {:#018x}, got {state:#018x}",
+            replay.final_state
+        )
+    }
+}
+
+/// Rasterizes a frame at `scale` times the game's resolution.
+pub(crate) fn rasterize<T: Game>(commands: &[DrawCommand], scale: usize) -> Vec<u32> {
+    let mut fb = FrameBuffer::new((T::WIDTH * scale) as u32, (T::HEIGHT * scale) as u32);
+    if scale == 1 {
+        fb.draw_list(commands);
+    } else {
+        for command in commands {
+            fb.draw(&command.scaled(scale as u32));
+        }
+    }
+    fb.pixels
+}
+
+/// The frame at `scale` times the game's resolution: rasterized at that size
+/// with `--crisp`, otherwise the logical `pixels` blown up.
+pub(crate) fn scaled_frame<T: Game>(
+    options: &Options,
+    commands: &[DrawCommand],
+    pixels: &[u32],
+    scale: usize,
+) -> Vec<u32> {
+    if options.crisp {
+        return rasterize::<T>(commands, scale);
+    }
+    let (width, height) = (T::WIDTH * scale, T::HEIGHT * scale);
+    let mut scaled = vec![0; width * height];
+    scale::upscale(pixels, T::WIDTH, T::HEIGHT, &mut scaled, width, height);
+    scaled
+}
+
+/// Saves a frame from [`scaled_frame`], without letterboxing or overlays.
+pub(crate) fn save_screenshot<T: Game>(
+    dir: &Path,
+    frame: Vec<u32>,
+    scale: usize,
+) -> io::Result<PathBuf> {
+    let (width, height) = (T::WIDTH * scale, T::HEIGHT * scale);
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}-{}.png", save::slug(T::NAME), timestamp()));
+    FrameBuffer::from_pixels(frame, width as u32, height as u32).write_png(&path)?;
+    Ok(path)
+}
+
+/// Saves a frame's commands as an SVG at the game's resolution.
+pub(crate) fn save_svg<T: Game>(dir: &Path, commands: &[DrawCommand]) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}-{}.svg", save::slug(T::NAME), timestamp()));
+    svg::write_svg(&path, commands, T::WIDTH as u32, T::HEIGHT as u32)?;
+    Ok(path)
+}
+
+pub(crate) fn new_clip<T: Game>() -> Gif {
+    Gif::new(T::WIDTH as u32, T::HEIGHT as u32, T::FPS as u32)
+}
+
+pub(crate) fn save_clip<T: Game>(dir: &Path, gif: Gif) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}-{}.gif", save::slug(T::NAME), timestamp()));
+    gif.save(&path)?;
+    Ok(path)
+}
+
+/// The current UTC time as `YYYYMMDD-HHMMSS-mmm`. Milliseconds keep captures
+/// taken in the same second from overwriting each other.
+fn timestamp() -> String {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let (secs, millis) = (elapsed.as_secs(), elapsed.subsec_millis());
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+
+    // Howard Hinnant's days_from_civil, inverted
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + i64::from(month <= 2);
+
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}-{millis:03}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
@@ -0,0 +1,52 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Numbered save slots, each holding a single 8 byte game state.
+pub struct SaveSlots {
+    dir: PathBuf,
+    game: String,
+}
+
+impl SaveSlots {
+    pub fn new(dir: impl Into<PathBuf>, game_name: &str) -> Self {
+        Self {
+            dir: dir.into(),
+            game: slug(game_name),
+        }
+    }
+
+    pub fn path(&self, slot: u8) -> PathBuf {
+        self.dir.join(format!("{}-{slot}.sav", self.game))
+    }
+
+    pub fn save(&self, slot: u8, state: u64) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(slot), state.to_le_bytes())
+    }
+
+    pub fn load(&self, slot: u8) -> io::Result<u64> {
+        let bytes = fs::read(self.path(slot))?;
+        let bytes = <[u8; 8]>::try_from(bytes.as_slice()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("save slot {slot} is not 8 bytes"),
+            )
+        })?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+/// A game name as used in file names, e.g. `Bitwise Breakout` becomes
+/// `bitwise-breakout`.
+pub(crate) fn slug(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,52 @@
+/// Where a scaled frame lands inside a larger buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: usize,
+    pub y: usize,
+    pub scale: usize,
+}
+
+impl Viewport {
+    /// The largest integer scale of a `width` x `height` frame that fits the
+    /// target, centred. Targets smaller than the frame get a scale of 1 and
+    /// are cropped.
+    pub fn fit(width: usize, height: usize, target_width: usize, target_height: usize) -> Self {
+        let scale = (target_width / width.max(1))
+            .min(target_height / height.max(1))
+            .max(1);
+        Self {
+            x: target_width.saturating_sub(width * scale) / 2,
+            y: target_height.saturating_sub(height * scale) / 2,
+            scale,
+        }
+    }
+}
+
+/// Nearest-neighbour upscales `pixels` into `target` by the largest integer
+/// factor that fits, filling the letterbox bars around it with black.
+pub fn upscale(
+    pixels: &[u32],
+    width: usize,
+    height: usize,
+    target: &mut [u32],
+    target_width: usize,
+    target_height: usize,
+) {
+    target.fill(0);
+    let viewport = Viewport::fit(width, height, target_width, target_height);
+
+    for (y, row) in pixels.chunks_exact(width.max(1)).take(height).enumerate() {
+        for dy in 0..viewport.scale {
+            let target_y = viewport.y + y * viewport.scale + dy;
+            if target_y >= target_height {
+                return;
+            }
+            let start = target_y * target_width + viewport.x;
+            let end = (target_y + 1) * target_width;
+            let target_row = &mut target[start..end];
+            for (target_pixel, &pixel) in target_row.chunks_mut(viewport.scale).zip(row.iter()) {
+                target_pixel.fill(pixel);
+            }
+        }
+    }
+}
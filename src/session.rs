@@ -0,0 +1,101 @@
+use crate::draw_command::DrawCommand;
+use crate::frame_buffer::FrameBuffer;
+use crate::game::Game;
+use crate::input::Input;
+use crate::rewind::RewindBuffer;
+use std::marker::PhantomData;
+
+/// Drives a [`Game`] one step at a time without any window.
+///
+/// Both the runners and [`run_headless`] are built on top of this.
+pub struct Session<T: Game> {
+    state: u64,
+    frame: u64,
+    game: PhantomData<T>,
+}
+
+impl<T: Game> Session<T> {
+    pub fn new(args: Vec<String>) -> Self {
+        Self::with_state(T::new(args))
+    }
+
+    /// Starts from an existing state, e.g. a replay's initial state.
+    pub fn with_state(state: u64) -> Self {
+        Self {
+            state,
+            frame: 0,
+            game: PhantomData,
+        }
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn set_state(&mut self, state: u64) {
+        self.state = state;
+    }
+
+    /// Draw commands for the current state.
+    pub fn render(&self) -> Vec<DrawCommand> {
+        T::render(self.state)
+    }
+
+    /// Number of steps run so far.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn step(&mut self, input: Input) {
+        self.state = T::step(self.state, input);
+        self.frame += 1;
+    }
+
+    /// Like [`Session::step`], but first remembers the current state in `history`.
+    pub fn step_recording(&mut self, input: Input, history: &mut RewindBuffer) {
+        history.push(self.state);
+        self.step(input);
+    }
+
+    /// Goes back one frame in `history`, returning `false` once it is empty.
+    pub fn step_back(&mut self, history: &mut RewindBuffer) -> bool {
+        let Some(state) = history.pop() else {
+            return false;
+        };
+        self.state = state;
+        self.frame = self.frame.saturating_sub(1);
+        true
+    }
+}
+
+/// Runs a game from `T::new(args)` through a scripted sequence of per-frame
+/// inputs, returning the state after each one. Nothing is rendered; pass any
+/// of the states to [`render_frame`] to see it.
+pub fn run_headless<T: Game>(args: Vec<String>, inputs: &[Input]) -> Vec<u64> {
+    let mut session = Session::<T>::new(args);
+    inputs
+        .iter()
+        .map(|&input| {
+            session.step(input);
+            session.state()
+        })
+        .collect()
+}
+
+/// Like [`run_headless`], but also rasterizes the state after each input.
+pub fn run_headless_frames<T: Game>(
+    args: Vec<String>,
+    inputs: &[Input],
+) -> Vec<(u64, FrameBuffer)> {
+    run_headless::<T>(args, inputs)
+        .into_iter()
+        .map(|state| (state, render_frame::<T>(state)))
+        .collect()
+}
+
+/// Rasterizes a state at the game's logical resolution.
+pub fn render_frame<T: Game>(state: u64) -> FrameBuffer {
+    let mut fb = FrameBuffer::new(T::WIDTH as u32, T::HEIGHT as u32);
+    fb.draw_list(&T::render(state));
+    fb
+}
@@ -0,0 +1,194 @@
+//! Golden image tests.
+//!
+//! A [`Snapshot`] runs a game headlessly from a given state, captures frames
+//! along the way and compares them with a reference PNG, the captured frames
+//! side by side:
+//!
+//! ```ignore
+//! Snapshot::<Breakout>::new("tests/snapshots", "serve", state)
+//!     .capture()
+//!     .step([Input::NONE; 10])
+//!     .capture()
+//!     .assert();
+//! ```
+//!
+//! On a mismatch the frames that were drawn are written next to the reference
+//! as `<name>.actual.png`, along with `<name>.diff.png` highlighting the pixels
+//! that differ. Running the tests with `UPDATE_SNAPSHOTS=1` writes the
+//! references instead.
+
+use crate::frame_buffer::FrameBuffer;
+use crate::game::Game;
+use crate::input::Input;
+use crate::png;
+use crate::runner;
+use crate::session::Session;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Set to anything but an empty string or `0` to write references instead of
+/// comparing.
+pub const UPDATE_VAR: &str = "UPDATE_SNAPSHOTS";
+
+const DIFF_COLOR: u32 = 0xff_00_4d;
+
+pub struct Snapshot<T: Game> {
+    dir: PathBuf,
+    name: String,
+    session: Session<T>,
+    frames: Vec<Vec<u32>>,
+}
+
+impl<T: Game> Snapshot<T> {
+    /// A snapshot whose reference image is `<dir>/<name>.png`.
+    pub fn new(dir: impl Into<PathBuf>, name: &str, state: u64) -> Self {
+        Self {
+            dir: dir.into(),
+            name: name.to_string(),
+            session: Session::with_state(state),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Runs one step per input.
+    pub fn step(&mut self, inputs: impl IntoIterator<Item = Input>) -> &mut Self {
+        for input in inputs {
+            self.session.step(input);
+        }
+        self
+    }
+
+    /// Renders the current state as the next frame of the snapshot.
+    pub fn capture(&mut self) -> &mut Self {
+        self.frames
+            .push(runner::rasterize::<T>(&self.session.render(), 1));
+        self
+    }
+
+    pub fn state(&self) -> u64 {
+        self.session.state()
+    }
+
+    /// Compares the captured frames with the reference image.
+    ///
+    /// # Panics
+    ///
+    /// If they differ, the reference is missing or no frame was captured.
+    pub fn assert(&self) {
+        assert!(
+            !self.frames.is_empty(),
+            "snapshot {} captured no frames",
+            self.name
+        );
+        let actual = self.strip();
+        let reference = self.path("png");
+
+        if updating() {
+            fs::create_dir_all(&self.dir).unwrap_or_else(|e| panic!("{e}"));
+            actual
+                .write_png(&reference)
+                .unwrap_or_else(|e| panic!("{e}"));
+            self.remove_failure_images();
+            return;
+        }
+
+        let expected = match fs::read(&reference) {
+            Ok(bytes) => png::decode(&bytes)
+                .unwrap_or_else(|| panic!("{} is not a PNG this crate wrote", reference.display())),
+            Err(err) => panic!(
+                "no reference image {}: {err}\nrun with {UPDATE_VAR}=1 to write it",
+                reference.display()
+            ),
+        };
+        let (expected_pixels, width, height) = expected;
+        let same_size = (width, height) == (actual.width, actual.height);
+        let different = if same_size {
+            differing_pixels(&expected_pixels, &actual.pixels)
+        } else {
+            actual.pixels.len()
+        };
+        if different == 0 {
+            self.remove_failure_images();
+            return;
+        }
+
+        let actual_path = self.path("actual.png");
+        actual
+            .write_png(&actual_path)
+            .unwrap_or_else(|e| panic!("{e}"));
+        let mut message = if same_size {
+            let diff_path = self.path("diff.png");
+            diff(&expected_pixels, &actual.pixels, width, height)
+                .write_png(&diff_path)
+                .unwrap_or_else(|e| panic!("{e}"));
+            format!(
+                "{different} pixels differ from {}, see {}",
+                reference.display(),
+                diff_path.display()
+            )
+        } else {
+            format!(
+                "snapshot is {}x{} but {} is {width}x{height}",
+                actual.width,
+                actual.height,
+                reference.display()
+            )
+        };
+        message += &format!(
+            "\nactual frames are in {}\nrun with {UPDATE_VAR}=1 to accept them",
+            actual_path.display()
+        );
+        panic!("snapshot {} failed: {message}", self.name);
+    }
+
+    /// The captured frames side by side.
+    fn strip(&self) -> FrameBuffer {
+        let width = T::WIDTH * self.frames.len();
+        let mut pixels = vec![0; width * T::HEIGHT];
+        for (i, frame) in self.frames.iter().enumerate() {
+            for (y, row) in frame.chunks(T::WIDTH).enumerate() {
+                let start = y * width + i * T::WIDTH;
+                pixels[start..start + T::WIDTH].copy_from_slice(row);
+            }
+        }
+        FrameBuffer::from_pixels(pixels, width as u32, T::HEIGHT as u32)
+    }
+
+    fn path(&self, extension: &str) -> PathBuf {
+        self.dir.join(format!("{}.{extension}", self.name))
+    }
+
+    /// Clears out images left behind by an earlier failure.
+    fn remove_failure_images(&self) {
+        for extension in ["actual.png", "diff.png"] {
+            let _ = fs::remove_file(self.path(extension));
+        }
+    }
+}
+
+/// Whether [`UPDATE_VAR`] asks for references to be written.
+pub fn updating() -> bool {
+    env::var(UPDATE_VAR).is_ok_and(|value| !value.is_empty() && value != "0")
+}
+
+fn differing_pixels(expected: &[u32], actual: &[u32]) -> usize {
+    expected.iter().zip(actual).filter(|(a, b)| a != b).count()
+}
+
+/// The expected image, faded to grey, with differing pixels marked.
+fn diff(expected: &[u32], actual: &[u32], width: u32, height: u32) -> FrameBuffer {
+    let pixels = expected
+        .iter()
+        .zip(actual)
+        .map(|(&e, &a)| {
+            if e != a {
+                return DIFF_COLOR;
+            }
+            let luma = (((e >> 16) & 0xff) * 3 + ((e >> 8) & 0xff) * 6 + (e & 0xff)) / 10;
+            let faded = 0x40 + luma / 4;
+            (faded << 16) | (faded << 8) | faded
+        })
+        .collect();
+    FrameBuffer::from_pixels(pixels, width, height)
+}
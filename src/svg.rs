@@ -0,0 +1,284 @@
+//! Writes frames as SVG documents.
+//!
+//! Every command becomes one SVG element in the frame's logical coordinates,
+//! so the image can be shown at any size. Shapes follow the rasterizer's
+//! conventions: pixel `(x, y)` is the unit square from `x` to `x + 1`, points
+//! sit at pixel centres and outlines are one pixel wide. Text and sprites are
+//! written out pixel by pixel, so they match `FrameBuffer` exactly.
+
+use crate::draw_command::{
+    BlendMode, Circle, Color, DrawCommand, Line, Rectangle, Sprite, Style, Text,
+};
+use crate::font;
+use crate::raster::Bounds;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// An SVG document `width` x `height` units in size, with the black
+/// background a new `FrameBuffer` starts with.
+pub fn to_svg(commands: &[DrawCommand], width: u32, height: u32) -> String {
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" shape-rendering="crispEdges">"#
+    );
+    let _ = writeln!(
+        svg,
+        r##"<rect width="{width}" height="{height}" fill="#000000"/>"##
+    );
+
+    let mut writer = Writer {
+        svg,
+        blend: String::new(),
+        clips: 0,
+        clipped: false,
+        width,
+        height,
+        bounds: Bounds::new(width, height, None),
+    };
+    for command in commands {
+        writer.command(command);
+    }
+    if writer.clipped {
+        writer.svg.push_str("</g>\n");
+    }
+
+    let mut svg = writer.svg;
+    svg.push_str("</svg>\n");
+    svg
+}
+
+pub fn write_svg(
+    path: impl AsRef<Path>,
+    commands: &[DrawCommand],
+    width: u32,
+    height: u32,
+) -> io::Result<()> {
+    fs::write(path, to_svg(commands, width, height))
+}
+
+struct Writer {
+    svg: String,
+    /// Style attribute for the current blend mode, empty for plain alpha.
+    blend: String,
+    clips: u32,
+    /// Whether a group for the current clip is open.
+    clipped: bool,
+    width: u32,
+    height: u32,
+    /// The visible area, for leaving out what would be clipped anyway.
+    bounds: Bounds,
+}
+
+impl Writer {
+    fn command(&mut self, command: &DrawCommand) {
+        match command {
+            DrawCommand::BlendMode(mode) => {
+                self.blend = match mode {
+                    BlendMode::Alpha => String::new(),
+                    BlendMode::Add => r#" style="mix-blend-mode:plus-lighter""#.to_string(),
+                    BlendMode::Multiply => r#" style="mix-blend-mode:multiply""#.to_string(),
+                };
+            }
+            DrawCommand::Clip(clip) => {
+                self.bounds = Bounds::new(self.width, self.height, *clip);
+                if self.clipped {
+                    self.svg.push_str("</g>\n");
+                    self.clipped = false;
+                }
+                if let Some(clip) = clip {
+                    self.clips += 1;
+                    let _ = writeln!(
+                        self.svg,
+                        r#"<clipPath id="clip{}"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath>"#,
+                        self.clips, clip.x, clip.y, clip.width, clip.height
+                    );
+                    let _ = writeln!(self.svg, r#"<g clip-path="url(#clip{})">"#, self.clips);
+                    self.clipped = true;
+                }
+            }
+            DrawCommand::Rectangle(rect) => self.rectangle(rect),
+            DrawCommand::Line(line) => self.line(line),
+            DrawCommand::Circle(circle) => self.circle(circle),
+            DrawCommand::Triangle(triangle) => {
+                let points = [
+                    (triangle.x1, triangle.y1),
+                    (triangle.x2, triangle.y2),
+                    (triangle.x3, triangle.y3),
+                ];
+                self.polygon(&points, &triangle.color, triangle.style);
+            }
+            DrawCommand::Polygon(polygon) => {
+                self.polygon(&polygon.points, &polygon.color, polygon.style);
+            }
+            DrawCommand::Text(text) => self.text(text),
+            DrawCommand::Sprite(sprite) => self.sprite(sprite),
+        }
+    }
+
+    fn rectangle(&mut self, rect: &Rectangle) {
+        let (x, y) = (f64::from(rect.x), f64::from(rect.y));
+        let (width, height) = (f64::from(rect.width), f64::from(rect.height));
+        let _ = match rect.style {
+            Style::Fill => writeln!(
+                self.svg,
+                r#"<rect x="{x}" y="{y}" width="{width}" height="{height}"{}/>"#,
+                self.paint(&rect.color, Style::Fill)
+            ),
+            // An outline one pixel thick is solid, and SVG wouldn't draw the
+            // zero-sized rect below for it
+            Style::Stroke if rect.width <= 1 || rect.height <= 1 => writeln!(
+                self.svg,
+                r#"<rect x="{x}" y="{y}" width="{width}" height="{height}"{}/>"#,
+                self.paint(&rect.color, Style::Fill)
+            ),
+            // The rasterizer draws the outline inside the rectangle
+            Style::Stroke => writeln!(
+                self.svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}"{}/>"#,
+                x + 0.5,
+                y + 0.5,
+                width - 1.0,
+                height - 1.0,
+                self.paint(&rect.color, Style::Stroke)
+            ),
+        };
+    }
+
+    fn line(&mut self, line: &Line) {
+        let _ = writeln!(
+            self.svg,
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke-linecap="square"{}/>"#,
+            f64::from(line.x1) + 0.5,
+            f64::from(line.y1) + 0.5,
+            f64::from(line.x2) + 0.5,
+            f64::from(line.y2) + 0.5,
+            self.paint(&line.color, Style::Stroke)
+        );
+    }
+
+    fn circle(&mut self, circle: &Circle) {
+        let radius = f64::from(circle.radius);
+        // Filled circles reach the edge of their outermost pixels, outlines
+        // run through the middle of them
+        let radius = match circle.style {
+            Style::Fill => radius + 0.5,
+            Style::Stroke => radius,
+        };
+        let _ = writeln!(
+            self.svg,
+            r#"<circle cx="{}" cy="{}" r="{radius}"{}/>"#,
+            f64::from(circle.x) + 0.5,
+            f64::from(circle.y) + 0.5,
+            self.paint(&circle.color, circle.style)
+        );
+    }
+
+    /// Polygons always get their outline, filled or not, as in the rasterizer.
+    fn polygon(&mut self, points: &[(i32, i32)], color: &Color, style: Style) {
+        let points = points
+            .iter()
+            .map(|&(x, y)| format!("{},{}", f64::from(x) + 0.5, f64::from(y) + 0.5))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let fill = match style {
+            Style::Fill => format!(r#"fill="{}" fill-rule="evenodd""#, hex(color)),
+            Style::Stroke => r#"fill="none""#.to_string(),
+        };
+        let _ = writeln!(
+            self.svg,
+            r#"<polygon points="{points}" {fill} stroke="{}" stroke-width="1" stroke-linejoin="round"{}/>"#,
+            hex(color),
+            self.effects(color)
+        );
+    }
+
+    fn text(&mut self, text: &Text) {
+        let scale = i64::from(text.scale.max(1));
+        let mut path = String::new();
+        for (row, line) in text.text.lines().enumerate() {
+            let y = i64::from(text.y) + row as i64 * i64::from(font::LINE_HEIGHT) * scale;
+            for (column, c) in line.chars().enumerate() {
+                let x = i64::from(text.x) + column as i64 * i64::from(font::ADVANCE) * scale;
+                for (dy, bits) in (0..).zip(font::glyph(c)) {
+                    for dx in 0..i64::from(font::GLYPH_WIDTH) {
+                        if bits & (0b100 >> dx) != 0 {
+                            push_square(&mut path, x + dx * scale, y + dy * scale, scale);
+                        }
+                    }
+                }
+            }
+        }
+        self.path(&path, &text.color);
+    }
+
+    /// One path per colour, each covering that colour's pixels.
+    fn sprite(&mut self, sprite: &Sprite) {
+        let scale = i64::from(sprite.scale.max(1));
+        let mut paths = vec![String::new(); sprite.colors.len()];
+        let (rows, columns) = self.bounds.sprite_cells(sprite);
+        for row in rows {
+            let source_y = if sprite.flip_y {
+                sprite.height - 1 - row
+            } else {
+                row
+            };
+            for column in columns.clone() {
+                let source_x = if sprite.flip_x {
+                    sprite.width - 1 - column
+                } else {
+                    column
+                };
+                let value = usize::from(sprite.value(source_x, source_y));
+                if let Some(path) = value.checked_sub(1).and_then(|i| paths.get_mut(i)) {
+                    let x = i64::from(sprite.x) + i64::from(column) * scale;
+                    let y = i64::from(sprite.y) + i64::from(row) * scale;
+                    push_square(path, x, y, scale);
+                }
+            }
+        }
+        for (path, color) in paths.iter().zip(&sprite.colors) {
+            self.path(path, color);
+        }
+    }
+
+    fn path(&mut self, path: &str, color: &Color) {
+        if !path.is_empty() {
+            let _ = writeln!(
+                self.svg,
+                r#"<path d="{path}"{}/>"#,
+                self.paint(color, Style::Fill)
+            );
+        }
+    }
+
+    /// Colour attributes for filling or outlining in `color`. Translucency
+    /// uses `opacity` so that a shape's fill and outline composite once, like
+    /// one rasterized command.
+    fn paint(&self, color: &Color, style: Style) -> String {
+        let paint = match style {
+            Style::Fill => format!(r#" fill="{}""#, hex(color)),
+            Style::Stroke => format!(r#" fill="none" stroke="{}" stroke-width="1""#, hex(color)),
+        };
+        paint + &self.effects(color)
+    }
+
+    /// The colour's opacity and the current blend mode.
+    fn effects(&self, color: &Color) -> String {
+        let mut effects = String::new();
+        if color.a < 255 {
+            let _ = write!(effects, r#" opacity="{:.3}""#, f64::from(color.a) / 255.0);
+        }
+        effects + &self.blend
+    }
+}
+
+fn hex(color: &Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+fn push_square(path: &mut String, x: i64, y: i64, size: i64) {
+    let _ = write!(path, "M{x} {y}h{size}v{size}h-{size}z");
+}
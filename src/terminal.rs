@@ -0,0 +1,614 @@
+//! Runs games in a terminal, for machines where no window can be opened.
+//!
+//! Every character cell shows two pixels stacked vertically: an upper half
+//! block in the top pixel's colour on a background of the bottom pixel's,
+//! using 24-bit ANSI colours. Only cells that changed are redrawn, which keeps
+//! the output small enough for SSH.
+//!
+//! Input is read from stdin in raw mode. Terminals report key presses and
+//! auto-repeats but never releases, so a button counts as held for a moment
+//! after each press.
+
+use crate::game::Game;
+use crate::input::{Button, Input};
+use crate::options::Options;
+use crate::replay::Replay;
+use crate::runner;
+use crate::save::SaveSlots;
+use crate::scale::Viewport;
+use std::env;
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a button stays down after a press. Long enough to bridge the
+/// delay before the terminal starts auto-repeating.
+const PRESS_HOLD: Duration = Duration::from_millis(500);
+
+/// How long a button stays down after each auto-repeat.
+const REPEAT_HOLD: Duration = Duration::from_millis(120);
+
+/// Escape also starts the sequences sent for arrow and function keys, which
+/// may arrive split across reads. A trailing escape only counts as the Escape
+/// key once nothing has followed it for this long.
+const ESCAPE_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Used when the terminal size cannot be read.
+const DEFAULT_SIZE: (usize, usize) = (80, 24);
+
+/// Runs a game in the terminal, taking the same flags as `run_game`.
+pub fn run_terminal<T: Game>() {
+    let (options, args) = Options::parse(env::args().collect()).unwrap_or_else(|e| panic!("{e}"));
+    run::<T>(options, args);
+}
+
+pub(crate) fn run<T: Game>(options: Options, args: Vec<String>) {
+    let replay = runner::load_replay(&options);
+    if runner::render_replay::<T>(&options, replay.as_ref()) {
+        return;
+    }
+
+    let slots = SaveSlots::new(&options.save_dir, T::NAME);
+    let mut session = runner::start_session::<T>(args, replay.as_ref(), options.load, &slots);
+    let mut recording = options
+        .record
+        .as_ref()
+        .map(|_| Replay::new(session.state()));
+    let mut replay_finished = false;
+    let mut clip = options.gif.as_ref().map(|_| runner::new_clip::<T>());
+    let mut audio = runner::Audio::new::<T>(&options);
+    let mut dump = runner::Dump::new(&options);
+    let mut held = HeldButtons::default();
+    // Printing would scroll the picture, so messages go in a status line
+    let mut message = String::new();
+
+    let raw_mode = RawMode::enter().unwrap_or_else(|e| panic!("{e}"));
+    let keys = spawn_stdin_reader();
+    let mut parser = KeyParser::default();
+    let mut screen = Screen::default();
+
+    let frame_duration = Duration::from_millis(1000 / T::FPS as u64);
+
+    'frames: loop {
+        let frame_start = Instant::now();
+
+        let mut bytes = Vec::new();
+        loop {
+            match keys.try_recv() {
+                Ok(chunk) => bytes.extend(chunk),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => break 'frames,
+            }
+        }
+        for key in parser.feed(&bytes, frame_start) {
+            match key {
+                TerminalKey::Quit => break 'frames,
+                TerminalKey::Button(button) => held.press(button, frame_start),
+                TerminalKey::Clip => {
+                    message = match clip.take() {
+                        Some(gif) => match runner::save_clip::<T>(&options.screenshot_dir, gif) {
+                            Ok(path) => format!("Saved clip {}", path.display()),
+                            Err(err) => format!("Could not save clip: {err}"),
+                        },
+                        None => {
+                            clip = Some(runner::new_clip::<T>());
+                            "Recording clip".to_string()
+                        }
+                    };
+                }
+                TerminalKey::SvgScreenshot => {
+                    let dir = &options.screenshot_dir;
+                    message = match runner::save_svg::<T>(dir, &session.render()) {
+                        Ok(path) => format!("Saved screenshot {}", path.display()),
+                        Err(err) => format!("Could not save screenshot: {err}"),
+                    };
+                }
+                TerminalKey::Screenshot => {
+                    let scale =
+                        Viewport::fit(T::WIDTH, T::HEIGHT, T::WINDOW_WIDTH, T::WINDOW_HEIGHT).scale;
+                    let commands = session.render();
+                    let pixels = runner::rasterize::<T>(&commands, 1);
+                    let frame = runner::scaled_frame::<T>(&options, &commands, &pixels, scale);
+                    message =
+                        match runner::save_screenshot::<T>(&options.screenshot_dir, frame, scale) {
+                            Ok(path) => format!("Saved screenshot {}", path.display()),
+                            Err(err) => format!("Could not save screenshot: {err}"),
+                        };
+                }
+            }
+        }
+
+        let input = match &replay {
+            Some(replay) => replay.inputs.get(session.frame() as usize).copied(),
+            None => Some(held.input(frame_start)),
+        };
+        let mut sounds = Vec::new();
+        match (input, &replay) {
+            (Some(input), _) => {
+                let previous = session.state();
+                session.step(input);
+                sounds = T::sounds(previous, session.state());
+                if let Some(recording) = &mut recording {
+                    recording.push(input, session.state());
+                }
+            }
+            (None, Some(replay)) if !replay_finished => {
+                replay_finished = true;
+                message = runner::replay_report(replay, session.state());
+            }
+            _ => {}
+        }
+        audio.frame(&sounds);
+        let commands = session.render();
+        if let Err(err) = dump.frame(&commands) {
+            message = format!("Stopped dumping frames: {err}");
+        }
+        let pixels = runner::rasterize::<T>(&commands, 1);
+        if let Some(gif) = &mut clip {
+            gif.push_pixels(&pixels);
+        }
+
+        let status = if options.debug {
+            format!(
+                "frame {} | state {:#018x} | {message}",
+                session.frame(),
+                session.state()
+            )
+        } else {
+            message.clone()
+        };
+        if screen.draw(&pixels, T::WIDTH, T::HEIGHT, &status).is_err() {
+            break;
+        }
+
+        let elapsed = frame_start.elapsed();
+        if elapsed < frame_duration {
+            thread::sleep(frame_duration - elapsed);
+        }
+    }
+
+    drop(raw_mode);
+    runner::finish::<T>(&options, recording, clip, audio);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TerminalKey {
+    Button(Button),
+    Quit,
+    Clip,
+    Screenshot,
+    SvgScreenshot,
+}
+
+/// Turns the bytes read from stdin into keys, holding on to escape sequences
+/// until they are complete.
+#[derive(Default)]
+struct KeyParser {
+    /// An escape sequence whose remaining bytes haven't arrived yet.
+    pending: Vec<u8>,
+    pending_since: Option<Instant>,
+}
+
+impl KeyParser {
+    /// Decodes the bytes typed since the last frame. Called every frame, even
+    /// with no bytes, so that a pending escape times out.
+    fn feed(&mut self, bytes: &[u8], now: Instant) -> Vec<TerminalKey> {
+        let timed_out = bytes.is_empty()
+            && self
+                .pending_since
+                .is_some_and(|since| now.duration_since(since) >= ESCAPE_TIMEOUT);
+        let mut input = std::mem::take(&mut self.pending);
+        input.extend_from_slice(bytes);
+
+        let (keys, rest) = parse_keys(&input, timed_out);
+        self.pending = rest.to_vec();
+        if self.pending.is_empty() {
+            self.pending_since = None;
+        } else {
+            self.pending_since.get_or_insert(now);
+        }
+        keys
+    }
+}
+
+/// Decodes `bytes`, using the same keys as the window where the terminal can
+/// send them. Returns the keys and an incomplete escape sequence left at the
+/// end, unless `flush` is set, in which case that escape is the Escape key.
+fn parse_keys(bytes: &[u8], flush: bool) -> (Vec<TerminalKey>, &[u8]) {
+    let mut keys = Vec::new();
+    let mut rest = bytes;
+    while let Some((&byte, tail)) = rest.split_first() {
+        let key = if byte == 0x1b {
+            match escape_sequence(tail) {
+                EscapeSequence::Incomplete if !flush => break,
+                EscapeSequence::Complete(len) => {
+                    let sequence;
+                    (sequence, rest) = tail.split_at(len);
+                    escape_key(sequence)
+                }
+                // Nothing that makes a sequence follows, so it's the Escape key itself
+                EscapeSequence::Incomplete | EscapeSequence::None => {
+                    rest = tail;
+                    Some(TerminalKey::Quit)
+                }
+            }
+        } else {
+            rest = tail;
+            byte_key(byte)
+        };
+        keys.extend(key);
+    }
+    (keys, rest)
+}
+
+fn escape_key(sequence: &[u8]) -> Option<TerminalKey> {
+    Some(match sequence {
+        b"[D" | b"OD" => TerminalKey::Button(Button::Left),
+        b"[C" | b"OC" => TerminalKey::Button(Button::Right),
+        b"[A" | b"OA" => TerminalKey::Button(Button::Up),
+        b"[B" | b"OB" => TerminalKey::Button(Button::Down),
+        b"[21~" => TerminalKey::Clip,
+        b"[24~" => TerminalKey::Screenshot,
+        b"[24;2~" => TerminalKey::SvgScreenshot,
+        _ => return None,
+    })
+}
+
+fn byte_key(byte: u8) -> Option<TerminalKey> {
+    Some(match byte {
+        // Raw mode delivers Ctrl+C as a byte rather than a signal
+        b'q' | 0x03 => TerminalKey::Quit,
+        b'z' | b'Z' | b' ' => TerminalKey::Button(Button::A),
+        b'x' | b'X' => TerminalKey::Button(Button::B),
+        b'\r' | b'\n' => TerminalKey::Button(Button::Start),
+        b'\t' => TerminalKey::Button(Button::Select),
+        _ => return None,
+    })
+}
+
+enum EscapeSequence {
+    /// A CSI (`ESC [`) or SS3 (`ESC O`) sequence of this many bytes after the
+    /// escape.
+    Complete(usize),
+    /// The start of a sequence, cut off before its final byte.
+    Incomplete,
+    None,
+}
+
+/// The escape sequence at the start of `bytes`, after the escape.
+fn escape_sequence(bytes: &[u8]) -> EscapeSequence {
+    match bytes.first() {
+        None => EscapeSequence::Incomplete,
+        Some(b'O') if bytes.len() >= 2 => EscapeSequence::Complete(2),
+        Some(b'O') => EscapeSequence::Incomplete,
+        Some(b'[') => bytes[1..]
+            .iter()
+            .position(|byte| (0x40..=0x7e).contains(byte))
+            .map_or(EscapeSequence::Incomplete, |end| {
+                EscapeSequence::Complete(end + 2)
+            }),
+        Some(_) => EscapeSequence::None,
+    }
+}
+
+/// When each button was last pressed, stretched out to cover the gaps
+/// between auto-repeats.
+#[derive(Default)]
+struct HeldButtons {
+    until: [Option<Instant>; 8],
+}
+
+impl HeldButtons {
+    fn press(&mut self, button: Button, now: Instant) {
+        let until = &mut self.until[button as usize];
+        let hold = match until {
+            Some(until) if *until > now => REPEAT_HOLD,
+            _ => PRESS_HOLD,
+        };
+        *until = Some(until.map_or(now + hold, |until| until.max(now + hold)));
+    }
+
+    fn input(&self, now: Instant) -> Input {
+        Button::ALL
+            .into_iter()
+            .filter(|&button| self.until[button as usize].is_some_and(|until| until > now))
+            .collect()
+    }
+}
+
+/// Reads stdin on its own thread so the game never waits for a key.
+fn spawn_stdin_reader() -> Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut stdin = io::stdin();
+        let mut buffer = [0; 64];
+        loop {
+            match stdin.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => {
+                    if sender.send(buffer[..read].to_vec()).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+    receiver
+}
+
+/// Puts the terminal in raw mode on the alternate screen until dropped, even
+/// if the game panics.
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enter() -> io::Result<Self> {
+        let saved = stty(&["-g"])?.trim().to_string();
+        stty(&["raw", "-echo"])?;
+        let mut stdout = io::stdout();
+        stdout.write_all(b"\x1b[?1049h\x1b[?25l\x1b[2J")?;
+        stdout.flush()?;
+        Ok(Self { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(b"\x1b[0m\x1b[?25h\x1b[?1049l");
+        let _ = stdout.flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!("stty failed: {}", err.trim())));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The terminal's size in columns and rows.
+fn terminal_size() -> (usize, usize) {
+    let size = stty(&["size"]).ok().and_then(|size| {
+        let (rows, columns) = size.trim().split_once(' ')?;
+        Some((columns.parse().ok()?, rows.parse().ok()?))
+    });
+    match size {
+        Some((columns, rows)) if columns > 0 && rows > 1 => (columns, rows),
+        _ => DEFAULT_SIZE,
+    }
+}
+
+/// What is currently on screen, so that each frame only redraws what changed.
+#[derive(Default)]
+struct Screen {
+    size: (usize, usize),
+    /// The top and bottom pixel shown in each cell.
+    cells: Vec<(u32, u32)>,
+    status: String,
+    frames: u64,
+}
+
+impl Screen {
+    /// Draws the frame in the top left corner with `status` below it. Frames
+    /// too big for the terminal are shrunk by the smallest integer factor
+    /// that fits.
+    fn draw(
+        &mut self,
+        pixels: &[u32],
+        width: usize,
+        height: usize,
+        status: &str,
+    ) -> io::Result<()> {
+        let mut out = String::new();
+
+        // Checking the size spawns a process, so only do it about once a second
+        if self.frames.is_multiple_of(30) {
+            let size = terminal_size();
+            if size != self.size {
+                self.size = size;
+                self.cells.clear();
+                self.status.clear();
+                out.push_str("\x1b[0m\x1b[2J");
+            }
+        }
+        self.frames += 1;
+
+        let (columns, rows) = self.size;
+        let factor = width
+            .div_ceil(columns)
+            .max(height.div_ceil(2 * (rows - 1)))
+            .max(1);
+        let frame_columns = width.div_ceil(factor);
+        let frame_rows = height.div_ceil(factor).div_ceil(2);
+        self.cells
+            .resize(frame_columns * frame_rows, (u32::MAX, u32::MAX));
+
+        let pixel = |x: usize, y: usize| {
+            let (x, y) = (x * factor, y * factor);
+            if y < height {
+                pixels.get(y * width + x).copied().unwrap_or(0)
+            } else {
+                0
+            }
+        };
+
+        let mut cursor = None;
+        let mut colors = None;
+        for row in 0..frame_rows {
+            for column in 0..frame_columns {
+                let cell = (pixel(column, row * 2), pixel(column, row * 2 + 1));
+                let shown = &mut self.cells[row * frame_columns + column];
+                if *shown == cell {
+                    continue;
+                }
+                *shown = cell;
+
+                if cursor != Some((row, column)) {
+                    let _ = write!(out, "\x1b[{};{}H", row + 1, column + 1);
+                }
+                if colors != Some(cell) {
+                    let (top, bottom) = cell;
+                    let _ = write!(
+                        out,
+                        "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+                        (top >> 16) & 0xff,
+                        (top >> 8) & 0xff,
+                        top & 0xff,
+                        (bottom >> 16) & 0xff,
+                        (bottom >> 8) & 0xff,
+                        bottom & 0xff
+                    );
+                    colors = Some(cell);
+                }
+                out.push('▀');
+                cursor = Some((row, column + 1));
+            }
+        }
+
+        if status != self.status {
+            self.status = status.to_string();
+            let status: String = status.chars().take(columns).collect();
+            let _ = write!(out, "\x1b[0m\x1b[{};1H{status}\x1b[K", frame_rows + 1);
+        } else if colors.is_some() {
+            out.push_str("\x1b[0m");
+        }
+
+        if out.is_empty() {
+            return Ok(());
+        }
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(out.as_bytes())?;
+        stdout.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT: TerminalKey = TerminalKey::Button(Button::Left);
+    const RIGHT: TerminalKey = TerminalKey::Button(Button::Right);
+
+    fn keys(bytes: &[u8]) -> Vec<TerminalKey> {
+        let (keys, rest) = parse_keys(bytes, false);
+        assert!(rest.is_empty(), "left over {rest:?}");
+        keys
+    }
+
+    #[test]
+    fn parses_keys() {
+        assert_eq!(
+            keys(b"zx\r\t "),
+            [
+                TerminalKey::Button(Button::A),
+                TerminalKey::Button(Button::B),
+                TerminalKey::Button(Button::Start),
+                TerminalKey::Button(Button::Select),
+                TerminalKey::Button(Button::A),
+            ]
+        );
+        assert_eq!(keys(b"q"), [TerminalKey::Quit]);
+        assert_eq!(keys(b"\x03"), [TerminalKey::Quit]);
+        assert_eq!(keys(b"a1?"), []);
+    }
+
+    #[test]
+    fn parses_escape_sequences() {
+        assert_eq!(keys(b"\x1b[D\x1bOC"), [LEFT, RIGHT]);
+        assert_eq!(
+            keys(b"\x1b[A\x1b[B"),
+            [
+                TerminalKey::Button(Button::Up),
+                TerminalKey::Button(Button::Down)
+            ]
+        );
+        assert_eq!(
+            keys(b"\x1b[21~\x1b[24~\x1b[24;2~"),
+            [
+                TerminalKey::Clip,
+                TerminalKey::Screenshot,
+                TerminalKey::SvgScreenshot
+            ]
+        );
+        // Unknown sequences are skipped whole
+        assert_eq!(keys(b"\x1b[1;5Dz"), [TerminalKey::Button(Button::A)]);
+    }
+
+    #[test]
+    fn escape_followed_by_a_key_is_escape() {
+        assert_eq!(
+            keys(b"\x1bz"),
+            [TerminalKey::Quit, TerminalKey::Button(Button::A)]
+        );
+        assert_eq!(keys(b"\x1b\x1b[C"), [TerminalKey::Quit, RIGHT]);
+    }
+
+    #[test]
+    fn keeps_incomplete_sequences() {
+        for split in [
+            b"\x1b".as_slice(),
+            b"\x1b[",
+            b"\x1bO",
+            b"\x1b[24",
+            b"\x1b[24;2",
+        ] {
+            let (keys, rest) = parse_keys(split, false);
+            assert_eq!(keys, [], "{split:?}");
+            assert_eq!(rest, split);
+        }
+        let (keys, rest) = parse_keys(b"x\x1b[", false);
+        assert_eq!(keys, [TerminalKey::Button(Button::B)]);
+        assert_eq!(rest, b"\x1b[");
+    }
+
+    #[test]
+    fn finishes_sequences_split_across_reads() {
+        let now = Instant::now();
+        let sequences: [&[u8]; 4] = [b"\x1b[C", b"\x1bOC", b"\x1b[24;2~", b"\x1b[D"];
+        let expected = [RIGHT, RIGHT, TerminalKey::SvgScreenshot, LEFT];
+        for (sequence, expected) in sequences.into_iter().zip(expected) {
+            for split in 1..sequence.len() {
+                let mut parser = KeyParser::default();
+                let (first, second) = sequence.split_at(split);
+                assert_eq!(parser.feed(first, now), [], "{sequence:?} at {split}");
+                // Within the timeout, a frame without input keeps waiting
+                assert_eq!(parser.feed(&[], now), [], "{sequence:?} at {split}");
+                let later = now + ESCAPE_TIMEOUT / 2;
+                assert_eq!(
+                    parser.feed(second, later),
+                    [expected],
+                    "{sequence:?} at {split}"
+                );
+                assert!(parser.pending.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn lone_escape_quits_after_the_timeout() {
+        let now = Instant::now();
+        let mut parser = KeyParser::default();
+        assert_eq!(parser.feed(b"\x1b", now), []);
+        assert_eq!(parser.feed(&[], now + ESCAPE_TIMEOUT / 2), []);
+        assert_eq!(parser.feed(&[], now + ESCAPE_TIMEOUT), [TerminalKey::Quit]);
+        assert_eq!(parser.feed(&[], now + ESCAPE_TIMEOUT * 2), []);
+
+        // A cut off sequence that never finishes is an Escape too, and the
+        // bytes after it are read as ordinary keys
+        let mut parser = KeyParser::default();
+        assert_eq!(parser.feed(b"\x1b[", now), []);
+        assert_eq!(parser.feed(&[], now + ESCAPE_TIMEOUT), [TerminalKey::Quit]);
+        assert!(parser.pending.is_empty());
+    }
+}
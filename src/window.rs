@@ -0,0 +1,255 @@
+use crate::debugger::Debugger;
+use crate::frame_buffer::FrameBuffer;
+use crate::game::Game;
+use crate::input::{Button, Input};
+use crate::inspector::Inspector;
+use crate::options::Options;
+use crate::replay::Replay;
+use crate::rewind::RewindBuffer;
+use crate::runner;
+use crate::save::SaveSlots;
+use crate::scale::{self, Viewport};
+use crate::terminal;
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use std::env;
+use std::time::{Duration, Instant};
+
+/// How much history the rewind buffer keeps.
+const REWIND_SECONDS: usize = 600;
+
+const SLOT_KEYS: [Key; 4] = [Key::F1, Key::F2, Key::F3, Key::F4];
+
+pub fn run_game<T: Game>() {
+    let (options, args) = Options::parse(env::args().collect()).unwrap_or_else(|e| panic!("{e}"));
+    if options.terminal {
+        terminal::run::<T>(options, args);
+        return;
+    }
+
+    let replay = runner::load_replay(&options);
+    if runner::render_replay::<T>(&options, replay.as_ref()) {
+        return;
+    }
+
+    let window_options = WindowOptions {
+        resize: true,
+        ..WindowOptions::default()
+    };
+    let mut window =
+        Window::new(T::NAME, T::WINDOW_WIDTH, T::WINDOW_HEIGHT, window_options).unwrap();
+    let mut display = Vec::new();
+
+    let slots = SaveSlots::new(&options.save_dir, T::NAME);
+    let mut session = runner::start_session::<T>(args, replay.as_ref(), options.load, &slots);
+    let mut recording = options
+        .record
+        .as_ref()
+        .map(|_| Replay::new(session.state()));
+    let mut replay_finished = false;
+    let mut history = RewindBuffer::new(T::FPS * REWIND_SECONDS);
+    let mut debugger = options.debug.then(Debugger::default);
+    let mut inspector: Option<Inspector> = None;
+    let mut clip = options.gif.as_ref().map(|_| runner::new_clip::<T>());
+    let mut audio = runner::Audio::new::<T>(&options);
+    let mut dump = runner::Dump::new(&options);
+
+    let frame_duration = Duration::from_millis(1000 / T::FPS as u64);
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        let frame_start = Instant::now();
+
+        for (slot, &key) in (1..).zip(&SLOT_KEYS) {
+            if !window.is_key_pressed(key, KeyRepeat::No) {
+                continue;
+            }
+            if is_shift_down(&window) {
+                if replay.is_some() {
+                    // Replay inputs are indexed by frame, so jumping the state would desync
+                    println!("Can't load slot {slot} while a replay is playing");
+                    continue;
+                }
+                match slots.load(slot) {
+                    Ok(state) => {
+                        session.set_state(state);
+                        // Loading jumps the state, so history and the recording restart here
+                        history.clear();
+                        if let Some(recording) = &mut recording {
+                            *recording = Replay::new(state);
+                        }
+                        println!("Loaded slot {slot}");
+                    }
+                    Err(err) => println!("Could not load slot {slot}: {err}"),
+                }
+            } else {
+                match slots.save(slot, session.state()) {
+                    Ok(()) => println!("Saved slot {slot}"),
+                    Err(err) => println!("Could not save slot {slot}: {err}"),
+                }
+            }
+        }
+
+        if let Some(debugger) = &mut debugger {
+            handle_debugger_keys(&window, debugger);
+        }
+        let advance = debugger.as_mut().is_none_or(Debugger::should_step);
+
+        let input = match &replay {
+            Some(replay) => replay.inputs.get(session.frame() as usize).copied(),
+            None => Some(input_from_keys(&window.get_keys())),
+        };
+
+        let mut sounds = Vec::new();
+        match (input, &replay) {
+            _ if window.is_key_down(Key::Backspace) => {
+                let rewound = session.step_back(&mut history);
+                if rewound && let Some(recording) = &mut recording {
+                    recording.pop(session.state());
+                }
+                // Back inside the replay, which plays on and reports again at its end
+                if let Some(replay) = &replay
+                    && session.frame() < replay.inputs.len() as u64
+                {
+                    replay_finished = false;
+                }
+            }
+            (Some(input), _) if advance => {
+                let previous = session.state();
+                session.step_recording(input, &mut history);
+                sounds = T::sounds(previous, session.state());
+                if let Some(recording) = &mut recording {
+                    recording.push(input, session.state());
+                }
+            }
+            (None, Some(replay)) if !replay_finished => {
+                replay_finished = true;
+                println!("{}", runner::replay_report(replay, session.state()));
+            }
+            _ => {}
+        }
+        audio.frame(&sounds);
+
+        if window.is_key_pressed(Key::F9, KeyRepeat::No) {
+            inspector = match inspector {
+                Some(_) => None,
+                None => Some(Inspector::default()),
+            };
+        }
+
+        if window.is_key_pressed(Key::F10, KeyRepeat::No) {
+            match clip.take() {
+                Some(gif) => match runner::save_clip::<T>(&options.screenshot_dir, gif) {
+                    Ok(path) => println!("Saved clip {}", path.display()),
+                    Err(err) => println!("Could not save clip: {err}"),
+                },
+                None => {
+                    clip = Some(runner::new_clip::<T>());
+                    println!("Recording clip");
+                }
+            }
+        }
+        let commands = session.render();
+        if let Err(err) = dump.frame(&commands) {
+            println!("Stopped dumping frames: {err}");
+        }
+        let pixels = runner::rasterize::<T>(&commands, 1);
+        if let Some(gif) = &mut clip {
+            gif.push_pixels(&pixels);
+        }
+
+        let (width, height) = window.get_size();
+        let scale = Viewport::fit(T::WIDTH, T::HEIGHT, width, height).scale;
+        let frame = runner::scaled_frame::<T>(&options, &commands, &pixels, scale);
+        if window.is_key_pressed(Key::F12, KeyRepeat::No) {
+            let dir = &options.screenshot_dir;
+            let saved = if is_shift_down(&window) {
+                runner::save_svg::<T>(dir, &commands)
+            } else {
+                runner::save_screenshot::<T>(dir, frame.clone(), scale)
+            };
+            match saved {
+                Ok(path) => println!("Saved screenshot {}", path.display()),
+                Err(err) => println!("Could not save screenshot: {err}"),
+            }
+        }
+
+        if width == 0 || height == 0 {
+            // Minimized, nothing to draw into
+            window.update();
+        } else {
+            display.resize(width * height, 0);
+            let (frame_width, frame_height) = (T::WIDTH * scale, T::HEIGHT * scale);
+            scale::upscale(
+                &frame,
+                frame_width,
+                frame_height,
+                &mut display,
+                width,
+                height,
+            );
+            if let Some(inspector) = &mut inspector {
+                inspector.observe(session.frame(), session.state());
+                let mut fb = FrameBuffer::from_pixels(display, width as u32, height as u32);
+                inspector.draw(&mut fb, T::LAYOUT);
+                display = fb.pixels;
+            }
+            window.update_with_buffer(&display, width, height).unwrap();
+        }
+
+        let mut frame_duration = frame_duration;
+        let mut title = match &debugger {
+            Some(debugger) => {
+                frame_duration = debugger.frame_duration(frame_duration);
+                debugger.title(T::NAME, session.frame(), session.state())
+            }
+            None => T::NAME.to_string(),
+        };
+        if let Some(inspector) = &inspector {
+            title = format!("{title} | {}", inspector.describe(T::LAYOUT));
+        }
+        window.set_title(&title);
+
+        let elapsed = frame_start.elapsed();
+        if elapsed < frame_duration {
+            std::thread::sleep(frame_duration - elapsed);
+        }
+    }
+
+    runner::finish::<T>(&options, recording, clip, audio);
+}
+
+fn handle_debugger_keys(window: &Window, debugger: &mut Debugger) {
+    if window.is_key_pressed(Key::F5, KeyRepeat::No) {
+        debugger.toggle_pause();
+    }
+    if window.is_key_pressed(Key::F6, KeyRepeat::Yes) {
+        debugger.request_step();
+    }
+    if window.is_key_pressed(Key::F7, KeyRepeat::No) {
+        debugger.slower();
+    }
+    if window.is_key_pressed(Key::F8, KeyRepeat::No) {
+        debugger.faster();
+    }
+}
+
+fn is_shift_down(window: &Window) -> bool {
+    window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift)
+}
+
+fn key_to_button(key: Key) -> Option<Button> {
+    match key {
+        Key::Left => Some(Button::Left),
+        Key::Right => Some(Button::Right),
+        Key::Up => Some(Button::Up),
+        Key::Down => Some(Button::Down),
+        Key::Z | Key::Space => Some(Button::A),
+        Key::X => Some(Button::B),
+        Key::Enter => Some(Button::Start),
+        Key::Tab => Some(Button::Select),
+        _ => None,
+    }
+}
+
+fn input_from_keys(keys: &[Key]) -> Input {
+    keys.iter().copied().filter_map(key_to_button).collect()
+}
@@ -0,0 +1,183 @@
+use bitwise_games::bit_layout;
+use bitwise_games::bits;
+use bitwise_games::inspector::Inspector;
+use bitwise_games::layout::BitLayout;
+
+const CASES: usize = 10_000;
+
+/// Deterministic xorshift generator so failures are reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u32) -> u32 {
+        (self.next() % u64::from(n)) as u32
+    }
+
+    /// A random field that fits in 64 bits.
+    fn field(&mut self) -> (u32, u32) {
+        let width = 1 + self.below(64);
+        let offset = self.below(65 - width);
+        (offset, width)
+    }
+}
+
+fn for_all(mut check: impl FnMut(&mut Rng)) {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..CASES {
+        check(&mut rng);
+    }
+}
+
+#[test]
+fn set_then_get_round_trips() {
+    for_all(|rng| {
+        let (value, field) = (rng.next(), rng.next());
+        let (offset, width) = rng.field();
+        let packed = bits::set(value, offset, width, field);
+        assert_eq!(bits::get(packed, offset, width), field & bits::mask(width));
+    });
+}
+
+#[test]
+fn set_leaves_other_bits_alone() {
+    for_all(|rng| {
+        let (value, field) = (rng.next(), rng.next());
+        let (offset, width) = rng.field();
+        let outside = !(bits::mask(width) << offset);
+        let packed = bits::set(value, offset, width, field);
+        assert_eq!(packed & outside, value & outside);
+    });
+}
+
+#[test]
+fn signed_fields_round_trip() {
+    for_all(|rng| {
+        let value = rng.next();
+        let (offset, width) = rng.field();
+        let field = bits::sign_extend(rng.next(), width);
+        let packed = bits::set_signed(value, offset, width, field);
+        assert_eq!(bits::get_signed(packed, offset, width), field);
+    });
+}
+
+#[test]
+fn sign_extend_matches_native_casts() {
+    for_all(|rng| {
+        let value = rng.next();
+        assert_eq!(bits::sign_extend(value, 8), i64::from(value as i8));
+        assert_eq!(bits::sign_extend(value, 16), i64::from(value as i16));
+        assert_eq!(bits::sign_extend(value, 32), i64::from(value as i32));
+        assert_eq!(bits::sign_extend(value, 64), value as i64);
+    });
+}
+
+#[test]
+fn flags_match_fields() {
+    for_all(|rng| {
+        let value = rng.next();
+        let bit = rng.below(64);
+        assert_eq!(bits::get_flag(value, bit), bits::get(value, bit, 1) == 1);
+        assert!(bits::get_flag(bits::set_flag(value, bit, true), bit));
+        assert!(!bits::get_flag(bits::set_flag(value, bit, false), bit));
+        assert_eq!(bits::toggle_flag(bits::toggle_flag(value, bit), bit), value);
+    });
+}
+
+#[test]
+fn iter_yields_each_flag() {
+    for_all(|rng| {
+        let value = rng.next();
+        let (offset, width) = rng.field();
+        let flags: Vec<bool> = bits::iter(value, offset, width).collect();
+        assert_eq!(flags.len(), width as usize);
+        for (i, &flag) in flags.iter().enumerate() {
+            assert_eq!(flag, bits::get_flag(value, offset + i as u32));
+        }
+    });
+}
+
+#[test]
+fn ones_rebuilds_the_value() {
+    for_all(|rng| {
+        let value = rng.next() & rng.next();
+        let indices: Vec<u32> = bits::ones(value).collect();
+        assert_eq!(indices.len() as u32, value.count_ones());
+        assert!(indices.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(indices.iter().fold(0, |acc, &i| acc | 1 << i), value);
+        assert_eq!(bits::first_set(value), indices.first().copied());
+        assert_eq!(bits::last_set(value), indices.last().copied());
+    });
+}
+
+#[test]
+fn count_ones_counts_within_field() {
+    for_all(|rng| {
+        let value = rng.next();
+        let (offset, width) = rng.field();
+        let expected = bits::iter(value, offset, width)
+            .filter(|&flag| flag)
+            .count();
+        assert_eq!(bits::count_ones(value, offset, width), expected as u32);
+    });
+}
+
+#[test]
+fn out_of_range_fields_do_not_panic() {
+    for_all(|rng| {
+        let (value, field) = (rng.next(), rng.next());
+        let offset = 64 + rng.below(64);
+        assert_eq!(bits::get(value, offset, 8), 0);
+        assert_eq!(bits::set(value, offset, 8, field), value);
+        assert!(!bits::get_flag(value, offset));
+        assert_eq!(bits::set_flag(value, offset, true), value);
+    });
+    assert_eq!(bits::get(u64::MAX, 60, 8), 0xf);
+    assert_eq!(bits::set(0, 60, 8, 0xff), 0xf << 60);
+}
+
+bit_layout! {
+    #[derive(Debug, PartialEq)]
+    struct Ship {
+        x: u8 = 6,
+        velocity: i8 = 3,
+        thrust: bool = 1,
+        angle: i16 = 10,
+    }
+}
+
+#[test]
+fn layouts_round_trip_signed_fields() {
+    for_all(|rng| {
+        let state = rng.next() & bits::mask(20);
+        let ship = Ship::from_u64(state);
+        assert!((-4..4).contains(&ship.velocity));
+        assert!((-512..512).contains(&ship.angle));
+        assert_eq!(ship.to_u64(), state);
+    });
+}
+
+#[test]
+fn layouts_record_signed_fields() {
+    let signed: Vec<bool> = Ship::FIELDS.iter().map(|field| field.signed).collect();
+    assert_eq!(signed, [false, true, false, true]);
+
+    let ship = Ship {
+        x: 42,
+        velocity: -1,
+        thrust: true,
+        angle: -300,
+    };
+    let mut inspector = Inspector::default();
+    inspector.observe(0, ship.to_u64());
+    assert_eq!(
+        inspector.describe(Ship::FIELDS),
+        "x=42 velocity=-1 thrust=1 angle=-300"
+    );
+}
@@ -0,0 +1,252 @@
+use bitwise_games::command_list::{self, CommandListError};
+use bitwise_games::draw_command::{
+    BlendMode, Circle, Clip, Color, DrawCommand, Line, Polygon, Rectangle, Sprite, Style, Text,
+    Triangle,
+};
+
+fn every_command() -> Vec<DrawCommand> {
+    let red = Color::from_rgb(255, 0, 77);
+    vec![
+        DrawCommand::Clip(Some(Clip {
+            x: -3,
+            y: 2,
+            width: 40,
+            height: 300,
+        })),
+        DrawCommand::BlendMode(BlendMode::Add),
+        DrawCommand::Rectangle(Rectangle {
+            x: -1000,
+            y: 5,
+            width: 64,
+            height: 1,
+            color: Color::from_rgba(1, 2, 3, 128),
+            style: Style::Stroke,
+        }),
+        DrawCommand::Line(Line {
+            x1: i32::MIN,
+            y1: i32::MAX,
+            x2: 0,
+            y2: -1,
+            color: red,
+        }),
+        DrawCommand::Circle(Circle {
+            x: 32,
+            y: 32,
+            radius: 6,
+            color: red,
+            style: Style::Fill,
+        }),
+        DrawCommand::Triangle(Triangle {
+            x1: 0,
+            y1: 0,
+            x2: 10,
+            y2: 0,
+            x3: 5,
+            y3: -8,
+            color: red,
+            style: Style::Fill,
+        }),
+        DrawCommand::Polygon(Polygon {
+            points: vec![(0, 0), (-4, 9), (12, 3)],
+            color: red,
+            style: Style::Stroke,
+        }),
+        DrawCommand::Polygon(Polygon {
+            points: Vec::new(),
+            color: red,
+            style: Style::Fill,
+        }),
+        DrawCommand::Text(Text {
+            x: 1,
+            y: 1,
+            text: "SCORE \"10\"\\ \n é".to_string(),
+            scale: 2,
+            color: red,
+        }),
+        DrawCommand::Sprite(Sprite {
+            flip_x: true,
+            scale: 3,
+            ..Sprite::mask(4, -2, 0x0018_3c7e_ff7e_3c18, red)
+        }),
+        DrawCommand::BlendMode(BlendMode::Alpha),
+        DrawCommand::Clip(None),
+    ]
+}
+
+#[test]
+fn binary_round_trips() {
+    let commands = every_command();
+    assert_eq!(
+        command_list::decode(&command_list::encode(&commands)).unwrap(),
+        commands
+    );
+    assert_eq!(
+        command_list::decode(&command_list::encode(&[])).unwrap(),
+        []
+    );
+}
+
+#[test]
+fn text_round_trips() {
+    let commands = every_command();
+    let text = command_list::to_text(&commands);
+    assert_eq!(command_list::from_text(&text).unwrap(), commands);
+    assert_eq!(text.lines().count(), commands.len() + 1);
+}
+
+#[test]
+fn text_is_readable() {
+    let commands = [
+        DrawCommand::Rectangle(Rectangle {
+            x: 0,
+            y: 0,
+            width: 64,
+            height: 64,
+            color: Color::from_rgb(0x1d, 0x2b, 0x53),
+            style: Style::Fill,
+        }),
+        DrawCommand::Circle(Circle {
+            x: 32,
+            y: 32,
+            radius: 6,
+            color: Color::from_rgba(0xff, 0xec, 0x27, 0x80),
+            style: Style::Stroke,
+        }),
+    ];
+    assert_eq!(
+        command_list::to_text(&commands),
+        "bitwise-draw 1\nrect 0 0 64 64 #1d2b53 fill\ncircle 32 32 6 #ffec2780 stroke\n"
+    );
+}
+
+#[test]
+fn frames_stream() {
+    let first = every_command();
+    let second = first[2..4].to_vec();
+    let mut stream = Vec::new();
+    command_list::write_frame(&mut stream, &first).unwrap();
+    command_list::write_frame(&mut stream, &second).unwrap();
+
+    let mut reader = stream.as_slice();
+    assert_eq!(command_list::read_frame(&mut reader).unwrap(), Some(first));
+    assert_eq!(command_list::read_frame(&mut reader).unwrap(), Some(second));
+    assert_eq!(command_list::read_frame(&mut reader).unwrap(), None);
+}
+
+#[test]
+fn rejects_bad_input() {
+    let bytes = command_list::encode(&every_command());
+    for len in 0..bytes.len() {
+        assert!(command_list::decode(&bytes[..len]).is_err(), "length {len}");
+    }
+    assert!(matches!(
+        command_list::decode(b"BWRP\x01\x00"),
+        Err(CommandListError::BadMagic)
+    ));
+    assert!(matches!(
+        command_list::decode(b"BWDC\x02\x00"),
+        Err(CommandListError::UnsupportedVersion(2))
+    ));
+    assert!(matches!(
+        command_list::decode(b"BWDC\x01\x01\x63"),
+        Err(CommandListError::UnknownCommand(0x63))
+    ));
+    assert!(matches!(
+        command_list::from_text("bitwise-draw 1\n\nrect 0 0 1\n"),
+        Err(CommandListError::Syntax { line: 3, .. })
+    ));
+    assert!(matches!(
+        command_list::from_text("bitwise-draw 1\nline 0 0 1 1 #fff\n"),
+        Err(CommandListError::Syntax { line: 2, .. })
+    ));
+}
+
+/// A one command list: the header, a count of one, then `command`.
+fn single(command: &[u8]) -> Vec<u8> {
+    [b"BWDC\x01\x01".as_slice(), command].concat()
+}
+
+#[test]
+fn rejects_invalid_fields() {
+    let invalid = |command: &[u8]| match command_list::decode(&single(command)) {
+        Err(CommandListError::InvalidField(field)) => field,
+        other => panic!("{command:?} decoded to {other:?}"),
+    };
+    // Blend mode 3
+    assert_eq!(invalid(b"\x00\x03"), "blend mode");
+    // Clip flag 2
+    assert_eq!(invalid(b"\x01\x02\x00\x00\x00\x00"), "clip");
+    // Rectangle with style 2
+    assert_eq!(
+        invalid(b"\x02\x00\x00\x01\x01\xff\xff\xff\xff\x02"),
+        "style"
+    );
+    // Rectangle 2^32 wide
+    assert_eq!(
+        invalid(b"\x02\x00\x00\x80\x80\x80\x80\x10\x01\xff\xff\xff\xff\x00"),
+        "number"
+    );
+    // Line from x = 2^31, which doesn't fit an i32 either
+    assert_eq!(
+        invalid(b"\x03\x80\x80\x80\x80\x10\x00\x00\x00\xff\xff\xff\xff"),
+        "number"
+    );
+    // A varint running past 64 bits
+    assert_eq!(
+        invalid(b"\x03\xff\xff\xff\xff\xff\xff\xff\xff\xff\x02"),
+        "number"
+    );
+    // Text that isn't UTF-8
+    assert_eq!(invalid(b"\x07\x00\x00\x01\xff\x01\xff\xff\xff\xff"), "text");
+    // Sprite flip 4
+    assert_eq!(
+        invalid(b"\x08\x00\x00\x01\x01\x01\x00\x00\x04\x01"),
+        "sprite flip"
+    );
+
+    // The largest values still fit
+    let line = DrawCommand::Line(Line {
+        x1: i32::MIN,
+        y1: i32::MAX,
+        x2: 0,
+        y2: 0,
+        color: Color::from_rgb(0, 0, 0),
+    });
+    let rect = DrawCommand::Rectangle(Rectangle {
+        x: 0,
+        y: 0,
+        width: u32::MAX,
+        height: 1,
+        color: Color::from_rgb(0, 0, 0),
+        style: Style::Fill,
+    });
+    let commands = [line, rect];
+    assert_eq!(
+        command_list::decode(&command_list::encode(&commands)).unwrap(),
+        commands
+    );
+}
+
+#[test]
+fn rejects_oversized_and_cut_off_frames() {
+    let too_large = (command_list::MAX_FRAME_BYTES as u32 + 1).to_le_bytes();
+    assert!(matches!(
+        command_list::read_frame(&mut too_large.as_slice()),
+        Err(CommandListError::FrameTooLarge(_))
+    ));
+    // A huge but allowed prefix with nothing behind it allocates nothing up front
+    let cut_off = (command_list::MAX_FRAME_BYTES as u32).to_le_bytes();
+    assert!(matches!(
+        command_list::read_frame(&mut cut_off.as_slice()),
+        Err(CommandListError::Truncated)
+    ));
+
+    let mut stream = Vec::new();
+    command_list::write_frame(&mut stream, &every_command()).unwrap();
+    for len in 1..stream.len() {
+        assert!(
+            command_list::read_frame(&mut &stream[..len]).is_err(),
+            "length {len}"
+        );
+    }
+}
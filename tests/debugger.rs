@@ -0,0 +1,59 @@
+use bitwise_games::debugger::Debugger;
+use std::time::Duration;
+
+#[test]
+fn single_steps_are_consumed_once() {
+    let mut debugger = Debugger::default();
+    assert!(debugger.should_step());
+
+    debugger.toggle_pause();
+    assert!(debugger.is_paused());
+    assert!(!debugger.should_step());
+
+    debugger.request_step();
+    debugger.request_step();
+    assert!(debugger.should_step());
+    assert!(!debugger.should_step());
+
+    // A step requested while running doesn't carry over into a pause
+    debugger.toggle_pause();
+    debugger.request_step();
+    assert!(debugger.should_step());
+    debugger.toggle_pause();
+    assert!(!debugger.should_step());
+}
+
+#[test]
+fn speeds_are_clamped() {
+    let base = Duration::from_millis(16);
+    let mut debugger = Debugger::default();
+    assert_eq!(debugger.frame_duration(base), base);
+
+    debugger.faster();
+    assert_eq!(debugger.frame_duration(base), base / 2);
+    for _ in 0..10 {
+        debugger.faster();
+    }
+    assert_eq!(debugger.speed(), 8.0);
+    assert_eq!(debugger.frame_duration(base), base / 8);
+
+    for _ in 0..20 {
+        debugger.slower();
+    }
+    assert_eq!(debugger.speed(), 1.0 / 16.0);
+    assert_eq!(debugger.frame_duration(base), base * 16);
+
+    debugger.faster();
+    assert_eq!(debugger.frame_duration(base), base * 8);
+}
+
+#[test]
+fn titles_show_the_frame_state_and_speed() {
+    let mut debugger = Debugger::default();
+    debugger.slower();
+    debugger.toggle_pause();
+    assert_eq!(
+        debugger.title("Breakout", 12, 0xff),
+        "Breakout | frame 12 | state 0x00000000000000ff | x0.5 | paused"
+    );
+}
@@ -0,0 +1,664 @@
+use bitwise_games::draw_command::{
+    BlendMode, Circle, Clip, Color, DrawCommand, Line, PICO8_PALETTE, Polygon, Rectangle, Sprite,
+    Style, Text, Triangle,
+};
+use bitwise_games::font;
+use bitwise_games::frame_buffer::FrameBuffer;
+use bitwise_games::indexed::IndexedFrameBuffer;
+use bitwise_games::svg;
+
+const RED: Color = Color::from_rgb(255, 0, 77);
+
+/// Draws `commands` on a black `width` x `height` frame and returns its rows,
+/// `#` for drawn pixels and `.` for black ones.
+fn draw(width: u32, height: u32, commands: &[DrawCommand]) -> Vec<String> {
+    let mut fb = FrameBuffer::new(width, height);
+    fb.draw_list(commands);
+    fb.pixels
+        .chunks(width as usize)
+        .map(|row| {
+            row.iter()
+                .map(|&pixel| if pixel == 0 { '.' } else { '#' })
+                .collect()
+        })
+        .collect()
+}
+
+fn text(x: i32, y: i32, text: &str, scale: u32) -> DrawCommand {
+    DrawCommand::Text(Text {
+        x,
+        y,
+        text: text.to_string(),
+        scale,
+        color: RED,
+    })
+}
+
+#[test]
+fn glyphs_render_row_by_row() {
+    assert_eq!(
+        draw(7, 5, &[text(0, 0, "1a", 1)]),
+        [".#...#.", "##..#.#", ".#..###", ".#..#.#", "###.#.#"]
+    );
+}
+
+#[test]
+fn every_printable_character_has_a_glyph() {
+    let unknown = font::glyph('\u{1f600}');
+    for c in '!'..='~' {
+        let glyph = font::glyph(c);
+        assert!(glyph.iter().any(|&row| row != 0), "{c:?} is blank");
+        assert!(glyph.iter().all(|&row| row < 8), "{c:?} is too wide");
+        if c != '?' {
+            assert_ne!(glyph, unknown, "{c:?} falls back to '?'");
+        }
+    }
+    assert_eq!(font::glyph(' '), [0; 5]);
+    assert_eq!(font::glyph('q'), font::glyph('Q'));
+    assert_eq!(unknown, font::glyph('?'));
+}
+
+#[test]
+fn text_scales_by_whole_pixels() {
+    assert_eq!(
+        draw(6, 10, &[text(0, 0, "1", 2)]),
+        [
+            "..##..", "..##..", "####..", "####..", "..##..", "..##..", "..##..", "..##..",
+            "######", "######",
+        ]
+    );
+    // Scale 0 draws as scale 1
+    assert_eq!(
+        draw(3, 5, &[text(0, 0, "1", 0)]),
+        draw(3, 5, &[text(0, 0, "1", 1)])
+    );
+}
+
+#[test]
+fn lines_start_below_each_other() {
+    assert_eq!(
+        draw(3, 11, &[text(0, 0, "-\n-", 1)]),
+        [
+            "...", "...", "###", "...", "...", "...", "...", "...", "###", "...", "...",
+        ]
+    );
+    assert_eq!(font::text_size("-\n--", 1), (7, 11));
+    assert_eq!(font::text_size("", 3), (0, 15));
+}
+
+#[test]
+fn text_is_clipped_at_every_edge() {
+    assert_eq!(draw(3, 3, &[text(-2, -2, "8", 1)]), ["#..", "#..", "#.."]);
+    assert_eq!(draw(3, 3, &[text(2, 1, "8", 1)]), ["...", "..#", "..#"]);
+    let blank = draw(3, 3, &[]);
+    for (x, y) in [(i32::MIN, 0), (i32::MAX, 0), (0, i32::MIN), (0, i32::MAX)] {
+        assert_eq!(draw(3, 3, &[text(x, y, "8888\n8888", 1)]), blank);
+    }
+    // One font pixel covering the whole frame
+    assert_eq!(draw(3, 3, &[text(-1, -1, "8", u32::MAX)]), ["###"; 3]);
+}
+
+fn circle(x: i32, y: i32, radius: u32, style: Style) -> DrawCommand {
+    DrawCommand::Circle(Circle {
+        x,
+        y,
+        radius,
+        color: RED,
+        style,
+    })
+}
+
+fn triangle(points: [(i32, i32); 3], style: Style) -> DrawCommand {
+    let [(x1, y1), (x2, y2), (x3, y3)] = points;
+    DrawCommand::Triangle(Triangle {
+        x1,
+        y1,
+        x2,
+        y2,
+        x3,
+        y3,
+        color: RED,
+        style,
+    })
+}
+
+fn polygon(points: &[(i32, i32)], style: Style) -> DrawCommand {
+    DrawCommand::Polygon(Polygon {
+        points: points.to_vec(),
+        color: RED,
+        style,
+    })
+}
+
+#[test]
+fn circles_fill_and_outline_the_same_pixels() {
+    assert_eq!(
+        draw(5, 5, &[circle(2, 2, 2, Style::Fill)]),
+        [".###.", "#####", "#####", "#####", ".###."]
+    );
+    assert_eq!(
+        draw(5, 5, &[circle(2, 2, 2, Style::Stroke)]),
+        [".###.", "#...#", "#...#", "#...#", ".###."]
+    );
+    for style in [Style::Fill, Style::Stroke] {
+        assert_eq!(draw(3, 3, &[circle(1, 1, 0, style)]), ["...", ".#.", "..."]);
+    }
+}
+
+#[test]
+fn triangles_fill_inside_their_outline() {
+    let points = [(0, 0), (5, 0), (0, 5)];
+    assert_eq!(
+        draw(6, 6, &[triangle(points, Style::Fill)]),
+        ["######", "#####.", "####..", "###...", "##....", "#....."]
+    );
+    assert_eq!(
+        draw(6, 6, &[triangle(points, Style::Stroke)]),
+        ["######", "#...#.", "#..#..", "#.#...", "##....", "#....."]
+    );
+}
+
+#[test]
+fn concave_polygons_leave_their_notch_empty() {
+    let points = [
+        (0, 0),
+        (6, 0),
+        (6, 4),
+        (4, 4),
+        (4, 2),
+        (2, 2),
+        (2, 4),
+        (0, 4),
+    ];
+    assert_eq!(
+        draw(7, 5, &[polygon(&points, Style::Fill)]),
+        ["#######", "#######", "#######", "###.###", "###.###"]
+    );
+    assert_eq!(
+        draw(7, 5, &[polygon(&points, Style::Stroke)]),
+        ["#######", "#.....#", "#.###.#", "#.#.#.#", "###.###"]
+    );
+}
+
+#[test]
+fn self_intersecting_polygons_fill_by_the_even_odd_rule() {
+    let bowtie = [(0, 0), (6, 4), (6, 0), (0, 4)];
+    assert_eq!(
+        draw(7, 5, &[polygon(&bowtie, Style::Fill)]),
+        ["#.....#", "###.###", "#######", "###.###", "#.....#"]
+    );
+
+    // Going round twice puts every inside point inside two edges
+    let twice = [(0, 0), (4, 0), (4, 4), (0, 4)].repeat(2);
+    assert_eq!(
+        draw(5, 5, &[polygon(&twice, Style::Fill)]),
+        draw(5, 5, &[polygon(&twice, Style::Stroke)])
+    );
+
+    let ring = [
+        (0, 0),
+        (8, 0),
+        (8, 8),
+        (0, 8),
+        (0, 0),
+        (2, 2),
+        (2, 6),
+        (6, 6),
+        (6, 2),
+        (2, 2),
+    ];
+    assert_eq!(
+        draw(9, 9, &[polygon(&ring, Style::Fill)]),
+        [
+            "#########",
+            "#########",
+            "#########",
+            "###...###",
+            "###...###",
+            "###...###",
+            "#########",
+            "#########",
+            "#########",
+        ]
+    );
+}
+
+#[test]
+fn degenerate_shapes_draw_their_outline() {
+    let line = draw(7, 7, &[triangle([(0, 0), (6, 6), (3, 3)], Style::Stroke)]);
+    assert_eq!(
+        line,
+        [
+            "#......", ".#.....", "..#....", "...#...", "....#..", ".....#.", "......#",
+        ]
+    );
+    assert_eq!(
+        draw(7, 7, &[triangle([(0, 0), (6, 6), (3, 3)], Style::Fill)]),
+        line
+    );
+
+    for style in [Style::Fill, Style::Stroke] {
+        assert_eq!(
+            draw(4, 3, &[polygon(&[(0, 1), (3, 1)], style)]),
+            ["....", "####", "...."]
+        );
+        assert_eq!(
+            draw(3, 3, &[polygon(&[(1, 1)], style)]),
+            ["...", ".#.", "..."]
+        );
+        assert_eq!(draw(3, 3, &[polygon(&[], style)]), ["..."; 3]);
+    }
+}
+
+fn fill(x: i32, y: i32, width: u32, height: u32, color: Color) -> DrawCommand {
+    DrawCommand::Rectangle(Rectangle {
+        x,
+        y,
+        width,
+        height,
+        color,
+        style: Style::Fill,
+    })
+}
+
+/// The pixel left by drawing `color` in `mode` over `(200, 100, 0)`.
+fn blend(mode: BlendMode, color: Color) -> u32 {
+    let mut fb = FrameBuffer::new(1, 1);
+    fb.draw_list(&[
+        fill(0, 0, 1, 1, Color::from_rgb(200, 100, 0)),
+        DrawCommand::BlendMode(mode),
+        fill(0, 0, 1, 1, color),
+    ]);
+    fb.pixels[0]
+}
+
+#[test]
+fn alpha_blending_mixes_by_opacity() {
+    let color = Color::from_rgba(0, 100, 255, 128);
+    assert_eq!(blend(BlendMode::Alpha, color), 0x646480);
+    assert_eq!(blend(BlendMode::Alpha, color.with_alpha(255)), 0x0064ff);
+    assert_eq!(blend(BlendMode::Alpha, color.with_alpha(0)), 0xc86400);
+}
+
+#[test]
+fn additive_blending_saturates() {
+    let color = Color::from_rgb(100, 200, 50);
+    assert_eq!(blend(BlendMode::Add, color), 0xffff32);
+    assert_eq!(blend(BlendMode::Add, color.with_alpha(128)), 0xe4b219);
+    assert_eq!(blend(BlendMode::Add, color.with_alpha(0)), 0xc86400);
+}
+
+#[test]
+fn multiply_blending_darkens() {
+    let color = Color::from_rgb(128, 255, 50);
+    assert_eq!(blend(BlendMode::Multiply, color), 0x646400);
+    assert_eq!(blend(BlendMode::Multiply, color.with_alpha(64)), 0xaf6400);
+    assert_eq!(blend(BlendMode::Multiply, color.with_alpha(0)), 0xc86400);
+}
+
+#[test]
+fn each_command_blends_a_pixel_once() {
+    let background = fill(0, 0, 7, 7, Color::from_rgb(200, 100, 0));
+    let half = Color::from_rgba(0, 100, 255, 128);
+
+    // Filled shapes draw their outline over their inside
+    let shapes = [
+        DrawCommand::Circle(Circle {
+            x: 3,
+            y: 3,
+            radius: 3,
+            color: half,
+            style: Style::Fill,
+        }),
+        DrawCommand::Triangle(Triangle {
+            x1: 0,
+            y1: 0,
+            x2: 6,
+            y2: 0,
+            x3: 3,
+            y3: 6,
+            color: half,
+            style: Style::Fill,
+        }),
+        DrawCommand::Polygon(Polygon {
+            points: [(0, 0), (6, 0), (6, 6), (0, 6)].repeat(2),
+            color: half,
+            style: Style::Fill,
+        }),
+    ];
+    for shape in shapes {
+        let mut fb = FrameBuffer::new(7, 7);
+        fb.draw_list(&[background.clone(), shape]);
+        for &pixel in &fb.pixels {
+            assert!(pixel == 0xc86400 || pixel == 0x646480, "{pixel:#08x}");
+        }
+    }
+
+    // Separate commands do compound
+    let mut fb = FrameBuffer::new(1, 1);
+    fb.draw_list(&[
+        fill(0, 0, 1, 1, Color::from_rgb(200, 100, 0)),
+        fill(0, 0, 1, 1, half),
+        fill(0, 0, 1, 1, half),
+    ]);
+    assert_eq!(fb.pixels[0], 0x3264c0);
+}
+
+#[test]
+fn lists_start_with_alpha_blending_and_no_clip() {
+    let mut fb = FrameBuffer::new(2, 2);
+    fb.draw_list(&[
+        DrawCommand::BlendMode(BlendMode::Multiply),
+        DrawCommand::Clip(Some(Clip {
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+        })),
+    ]);
+    fb.draw(&fill(0, 0, 2, 2, RED));
+    assert_eq!(fb.pixels, [0, 0, 0, 0]);
+
+    fb.draw_list(&[fill(0, 0, 2, 2, RED)]);
+    assert_eq!(fb.pixels, [0xff004d; 4]);
+}
+
+const MIN: i32 = i32::MIN;
+const MAX: i32 = i32::MAX;
+
+fn line(x1: i32, y1: i32, x2: i32, y2: i32) -> DrawCommand {
+    DrawCommand::Line(Line {
+        x1,
+        y1,
+        x2,
+        y2,
+        color: RED,
+    })
+}
+
+fn clip(x: i32, y: i32, width: u32, height: u32) -> DrawCommand {
+    DrawCommand::Clip(Some(Clip {
+        x,
+        y,
+        width,
+        height,
+    }))
+}
+
+#[test]
+fn rectangles_are_clipped_at_extreme_coordinates() {
+    let everything = fill(MIN, MIN, u32::MAX, u32::MAX, RED);
+    assert_eq!(draw(4, 4, &[everything]), ["####"; 4]);
+    assert_eq!(
+        draw(4, 4, &[fill(-2, 3, 4, 9, RED)]),
+        ["....", "....", "....", "##.."]
+    );
+    for (x, y) in [(MIN, 0), (MAX, 0), (0, MIN), (0, MAX), (-4, -4), (4, 4)] {
+        assert_eq!(draw(4, 4, &[fill(x, y, 4, 4, RED)]), ["...."; 4]);
+    }
+
+    // The edges of a huge outline are all off screen
+    let outline = DrawCommand::Rectangle(Rectangle {
+        x: MIN,
+        y: MIN,
+        width: u32::MAX,
+        height: u32::MAX,
+        color: RED,
+        style: Style::Stroke,
+    });
+    assert_eq!(draw(4, 4, &[outline]), ["...."; 4]);
+    let outline = DrawCommand::Rectangle(Rectangle {
+        x: -1,
+        y: -1,
+        width: 4,
+        height: 4,
+        color: RED,
+        style: Style::Stroke,
+    });
+    assert_eq!(draw(4, 4, &[outline]), ["..#.", "..#.", "###.", "...."]);
+}
+
+#[test]
+fn lines_are_clipped_at_extreme_coordinates() {
+    assert_eq!(
+        draw(4, 4, &[line(MIN, MIN, MAX, MAX)]),
+        ["#...", ".#..", "..#.", "...#"]
+    );
+    assert_eq!(
+        draw(4, 4, &[line(MAX, 0, MIN, 0), line(1, MIN, 1, MAX)]),
+        ["####", ".#..", ".#..", ".#.."]
+    );
+    // Lines passing by the frame
+    assert_eq!(draw(4, 4, &[line(MIN, MAX, MAX, MIN)]), ["...."; 4]);
+    assert_eq!(draw(4, 4, &[line(-10, 2, 2, -10)]), ["...."; 4]);
+    assert_eq!(draw(4, 4, &[line(MIN, -1, MAX, -1)]), ["...."; 4]);
+}
+
+#[test]
+fn circles_are_clipped_at_extreme_coordinates() {
+    assert_eq!(
+        draw(4, 4, &[circle(MIN, MIN, u32::MAX, Style::Fill)]),
+        ["####"; 4]
+    );
+    assert_eq!(
+        draw(4, 4, &[circle(0, 0, u32::MAX, Style::Stroke)]),
+        ["...."; 4]
+    );
+    assert_eq!(
+        draw(4, 4, &[circle(-2, 1, 3, Style::Stroke)]),
+        [".#..", ".#..", ".#..", "#..."]
+    );
+    for (x, y) in [(MIN, MIN), (MAX, MAX), (MIN, 0), (0, MAX)] {
+        assert_eq!(draw(4, 4, &[circle(x, y, 10, Style::Fill)]), ["...."; 4]);
+    }
+}
+
+#[test]
+fn polygons_are_clipped_at_extreme_coordinates() {
+    let half = [(MIN, MIN), (MAX, MAX), (MIN, MAX)];
+    assert_eq!(
+        draw(4, 4, &[triangle(half, Style::Fill)]),
+        ["#...", "##..", "###.", "####"]
+    );
+    assert_eq!(
+        draw(4, 4, &[triangle(half, Style::Stroke)]),
+        ["#...", ".#..", "..#.", "...#"]
+    );
+
+    let square = [(MIN, MIN), (MAX, MIN), (MAX, MAX), (MIN, MAX)];
+    assert_eq!(draw(4, 4, &[polygon(&square, Style::Fill)]), ["####"; 4]);
+    assert_eq!(draw(4, 4, &[polygon(&square, Style::Stroke)]), ["...."; 4]);
+}
+
+#[test]
+fn clips_narrow_every_command() {
+    let commands = |clip_command: DrawCommand| {
+        vec![
+            clip_command,
+            fill(MIN, MIN, u32::MAX, u32::MAX, RED),
+            line(0, 0, 3, 3),
+            circle(1, 1, 5, Style::Fill),
+            polygon(&[(0, 0), (3, 0), (0, 3)], Style::Fill),
+            text(0, 0, "8", 2),
+        ]
+    };
+    assert_eq!(
+        draw(4, 4, &commands(clip(1, 1, 2, 2))),
+        ["....", ".##.", ".##.", "...."]
+    );
+    // Clips partly or wholly outside the frame
+    assert_eq!(
+        draw(4, 4, &commands(clip(MIN, 2, u32::MAX, u32::MAX))),
+        ["....", "....", "####", "####"]
+    );
+    for empty in [
+        clip(0, 0, 0, 4),
+        clip(0, 0, 4, 0),
+        clip(4, 0, 4, 4),
+        clip(0, -4, 4, 4),
+        clip(MAX, MAX, u32::MAX, u32::MAX),
+        clip(MIN, MIN, 1, 1),
+    ] {
+        assert_eq!(draw(4, 4, &commands(empty)), ["...."; 4]);
+    }
+}
+
+#[test]
+fn clips_replace_each_other() {
+    assert_eq!(
+        draw(
+            4,
+            4,
+            &[
+                clip(0, 0, 2, 2),
+                clip(2, 2, 2, 2),
+                fill(0, 0, 4, 4, RED),
+                DrawCommand::Clip(None),
+                line(0, 0, 3, 0),
+            ]
+        ),
+        ["####", "....", "..##", "..##"]
+    );
+    // An empty clip doesn't carry over into the next one
+    assert_eq!(
+        draw(
+            4,
+            4,
+            &[clip(0, 0, 0, 0), clip(0, 0, 1, 1), fill(0, 0, 4, 4, RED)]
+        ),
+        ["#...", "....", "....", "...."]
+    );
+}
+
+#[test]
+fn huge_sprites_do_not_overflow_bit_indices() {
+    // The visible pixels are around row and column 2^31, whose bit index at
+    // 4 bits a pixel is past u64::MAX
+    let sprite = Sprite {
+        x: MIN,
+        y: MIN,
+        width: u32::MAX,
+        height: u32::MAX,
+        depth: 4,
+        data: vec![u64::MAX; 4],
+        colors: vec![RED; 15],
+        flip_x: false,
+        flip_y: false,
+        scale: 1,
+    };
+    assert_eq!(sprite.value(u32::MAX - 1, u32::MAX - 1), 0);
+    assert_eq!(sprite.value(1, 0), 15);
+
+    let commands = [DrawCommand::Sprite(sprite)];
+    assert_eq!(draw(4, 4, &commands), ["...."; 4]);
+    let mut indexed = IndexedFrameBuffer::new(4, 4);
+    indexed.draw_list(&commands);
+    assert_eq!(indexed.indices, [0; 16]);
+    assert!(!svg::to_svg(&commands, 4, 4).contains("<path"));
+}
+
+/// Like [`draw`], but each drawn pixel is shown as the hex digit of its
+/// PICO-8 palette index.
+fn paint(width: u32, height: u32, commands: &[DrawCommand]) -> Vec<String> {
+    let mut fb = FrameBuffer::new(width, height);
+    fb.draw_list(commands);
+    let digit = |pixel: u32| {
+        (0..)
+            .zip(&PICO8_PALETTE)
+            .find(|(_, color)| {
+                pixel == (u32::from(color.r) << 16 | u32::from(color.g) << 8 | u32::from(color.b))
+            })
+            .filter(|_| pixel != 0)
+            .map_or('.', |(i, _)| char::from_digit(i, 16).unwrap())
+    };
+    fb.pixels
+        .chunks(width as usize)
+        .map(|row| row.iter().map(|&pixel| digit(pixel)).collect())
+        .collect()
+}
+
+fn sprite(width: u32, height: u32, depth: u32, data: u64, colors: &[usize]) -> Sprite {
+    Sprite {
+        x: 0,
+        y: 0,
+        width,
+        height,
+        depth,
+        data: vec![data],
+        colors: colors.iter().map(|&i| PICO8_PALETTE[i]).collect(),
+        flip_x: false,
+        flip_y: false,
+        scale: 1,
+    }
+}
+
+#[test]
+fn sprites_draw_at_every_depth() {
+    let mask = Sprite::mask(0, 0, 0x81 | 0x42 << 8, RED);
+    assert_eq!(
+        paint(8, 3, &[DrawCommand::Sprite(mask)]),
+        ["8......8", ".8....8.", "........"]
+    );
+
+    let two_bits = sprite(3, 2, 2, 1 | 2 << 2 | 3 << 4 | 1 << 8, &[8, 11, 12]);
+    assert_eq!(
+        paint(3, 2, &[DrawCommand::Sprite(two_bits)]),
+        ["8bc", ".8."]
+    );
+
+    let all: Vec<usize> = (0..16).collect();
+    let four_bits = sprite(4, 1, 4, 0xf | 0x8 << 4 | 0x1 << 12, &all[1..]);
+    assert_eq!(paint(4, 1, &[DrawCommand::Sprite(four_bits)]), ["f8.1"]);
+
+    // Values without a colour are transparent
+    let eight_bits = sprite(3, 1, 8, 0xff | 0x10 << 8 | 0x0c << 16, &all[1..]);
+    assert_eq!(paint(3, 1, &[DrawCommand::Sprite(eight_bits)]), ["..c"]);
+
+    let mut wide = sprite(9, 1, 8, 0, &[8]);
+    wide.data = vec![0, 1];
+    assert_eq!((wide.value(7, 0), wide.value(8, 0)), (0, 1));
+    assert_eq!(paint(9, 1, &[DrawCommand::Sprite(wide)]), ["........8"]);
+}
+
+#[test]
+fn sprites_of_other_depths_draw_nothing() {
+    for depth in [0, 3, 5, 16, 64] {
+        let sprite = sprite(2, 2, depth, u64::MAX, &[8; 255]);
+        assert_eq!(sprite.value(0, 0), 0, "depth {depth}");
+        assert_eq!(
+            paint(2, 2, &[DrawCommand::Sprite(sprite)]),
+            ["..", ".."],
+            "depth {depth}"
+        );
+    }
+}
+
+#[test]
+fn sprites_flip_and_scale() {
+    let base = sprite(3, 2, 2, 1 | 2 << 2 | 3 << 4 | 1 << 8, &[8, 11, 12]);
+    for (flip_x, flip_y, rows) in [
+        (false, false, ["8bc", ".8."]),
+        (true, false, ["cb8", ".8."]),
+        (false, true, [".8.", "8bc"]),
+        (true, true, [".8.", "cb8"]),
+    ] {
+        let flipped = Sprite {
+            flip_x,
+            flip_y,
+            ..base.clone()
+        };
+        assert_eq!(paint(3, 2, &[DrawCommand::Sprite(flipped)]), rows);
+    }
+
+    let scaled = Sprite {
+        x: 1,
+        y: -1,
+        flip_x: true,
+        scale: 2,
+        ..base
+    };
+    assert_eq!(
+        paint(7, 3, &[DrawCommand::Sprite(scaled)]),
+        [".ccbb88", "...88..", "...88.."]
+    );
+}
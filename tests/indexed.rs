@@ -0,0 +1,88 @@
+use bitwise_games::draw_command::{
+    BLUE, Color, DrawCommand, PICO8_PALETTE, RED, Rectangle, Style, WHITE,
+};
+use bitwise_games::indexed::IndexedFrameBuffer;
+
+fn fill(x: i32, y: i32, width: u32, height: u32, color: Color) -> DrawCommand {
+    DrawCommand::Rectangle(Rectangle {
+        x,
+        y,
+        width,
+        height,
+        color,
+        style: Style::Fill,
+    })
+}
+
+fn rgb(color: &Color) -> u32 {
+    (u32::from(color.r) << 16) | (u32::from(color.g) << 8) | u32::from(color.b)
+}
+
+#[test]
+fn palette_colours_round_trip_through_their_index() {
+    let mut fb = IndexedFrameBuffer::new(16, 1);
+    for (x, color) in (0..).zip(&PICO8_PALETTE) {
+        assert_eq!(usize::from(fb.index_of(color)), x as usize);
+        fb.draw(&fill(x, 0, 1, 1, *color));
+    }
+    let expected: Vec<u32> = PICO8_PALETTE.iter().map(rgb).collect();
+    assert_eq!(fb.present(), expected);
+
+    // Other colours land on the nearest entry, and indices past the end of
+    // the palette show as its first colour
+    assert_eq!(fb.index_of(&Color::from_rgb(250, 10, 70)), 8);
+    assert_eq!(fb.index_of(&Color::from_rgb(1, 2, 3)), 0);
+    fb.set_pixel(0, 0, 200);
+    assert_eq!(fb.present()[0], rgb(&PICO8_PALETTE[0]));
+}
+
+#[test]
+fn remapping_changes_what_is_drawn_from_then_on() {
+    let mut fb = IndexedFrameBuffer::new(2, 1);
+    fb.draw(&fill(0, 0, 1, 1, RED));
+    fb.remap(8, 12);
+    fb.draw(&fill(1, 0, 1, 1, RED));
+    assert_eq!(fb.indices, [8, 12]);
+    assert_eq!(fb.present(), [rgb(&RED), rgb(&BLUE)]);
+
+    fb.reset_maps();
+    fb.clear(8);
+    assert_eq!(fb.indices, [8, 8]);
+}
+
+#[test]
+fn screen_remapping_changes_only_what_is_shown() {
+    let mut fb = IndexedFrameBuffer::new(2, 1);
+    fb.draw_list(&[fill(0, 0, 1, 1, RED), fill(1, 0, 1, 1, WHITE)]);
+    fb.remap_screen(8, 7);
+    assert_eq!(fb.indices, [8, 7]);
+    assert_eq!(fb.present(), [rgb(&WHITE), rgb(&WHITE)]);
+
+    fb.reset_maps();
+    assert_eq!(fb.present(), [rgb(&RED), rgb(&WHITE)]);
+}
+
+#[test]
+fn nibbles_round_trip_at_odd_widths() {
+    for (width, height) in [(1, 1), (3, 2), (5, 3), (8, 2), (0, 4), (4, 0)] {
+        let mut fb = IndexedFrameBuffer::new(width, height);
+        for (i, index) in fb.indices.iter_mut().enumerate() {
+            *index = (i * 7 % 16) as u8;
+        }
+        let nibbles = fb.to_nibbles();
+        assert_eq!(
+            nibbles.len(),
+            width.div_ceil(2) as usize * height as usize,
+            "{width}x{height}"
+        );
+        let unpacked = IndexedFrameBuffer::from_nibbles(width, height, &nibbles);
+        assert_eq!(unpacked.indices, fb.indices, "{width}x{height}");
+    }
+
+    // Rows start on a new byte, with the left pixel in the low nibble
+    let mut fb = IndexedFrameBuffer::new(3, 2);
+    fb.indices.copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+    assert_eq!(fb.to_nibbles(), [0x21, 0x03, 0x54, 0x06]);
+    fb.indices[0] = 0xf1;
+    assert_eq!(fb.to_nibbles()[0], 0x21);
+}
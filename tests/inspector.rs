@@ -0,0 +1,39 @@
+use bitwise_games::frame_buffer::FrameBuffer;
+use bitwise_games::inspector::Inspector;
+
+#[test]
+fn nothing_has_changed_before_a_step() {
+    let mut inspector = Inspector::default();
+    inspector.observe(100, 0xf0f0);
+    assert_eq!(inspector.changed(), 0);
+    inspector.observe(100, 0xf0f0);
+    assert_eq!(inspector.changed(), 0);
+
+    inspector.observe(101, 0xff00);
+    assert_eq!(inspector.changed(), 0x0ff0);
+    // Only a new frame updates the changed bits
+    inspector.observe(101, 0xff00);
+    assert_eq!(inspector.changed(), 0x0ff0);
+    inspector.observe(100, 0xf0f0);
+    assert_eq!(inspector.changed(), 0x0ff0);
+}
+
+#[test]
+fn unchanged_bits_are_not_outlined() {
+    // Bits that are set but unchanged are filled, never outlined in white
+    let outlined = |inspector: &Inspector| {
+        let mut fb = FrameBuffer::new(256, 256);
+        inspector.draw(&mut fb, &[]);
+        fb.pixels
+            .iter()
+            .filter(|&&pixel| pixel == 0xff_f1_e8)
+            .count()
+    };
+
+    let mut inspector = Inspector::default();
+    inspector.observe(100, u64::MAX);
+    assert_eq!(outlined(&inspector), 0);
+
+    inspector.observe(101, u64::MAX ^ 1);
+    assert_eq!(outlined(&inspector), 4 * 8 - 4);
+}
@@ -0,0 +1,125 @@
+use bitwise_games::draw_command::DrawCommand;
+use bitwise_games::output::{self, SAMPLE_RATE, Sound, Synth, Waveform};
+use bitwise_games::replay::Replay;
+use bitwise_games::{Button, Game, Input};
+use std::fs;
+use std::time::Duration;
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[test]
+fn wav_files_have_a_pcm_header_and_every_sample() {
+    let samples = [0, 1, -1, i16::MAX, i16::MIN, 1234];
+    let path = std::env::temp_dir().join(format!("bitwise-games-{}.wav", std::process::id()));
+    output::write_wav(&path, &samples, 22_050).unwrap();
+    let wav = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(wav.len(), 44 + samples.len() * 2);
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(u32_at(&wav, 4) as usize, wav.len() - 8);
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(u32_at(&wav, 16), 16);
+    // PCM, mono, 22050 Hz, 2 bytes a frame, 16 bits a sample
+    assert_eq!(u16_at(&wav, 20), 1);
+    assert_eq!(u16_at(&wav, 22), 1);
+    assert_eq!(u32_at(&wav, 24), 22_050);
+    assert_eq!(u32_at(&wav, 28), 44_100);
+    assert_eq!(u16_at(&wav, 32), 2);
+    assert_eq!(u16_at(&wav, 34), 16);
+    assert_eq!(&wav[36..40], b"data");
+    assert_eq!(u32_at(&wav, 40) as usize, samples.len() * 2);
+
+    let decoded: Vec<i16> = wav[44..]
+        .chunks(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    assert_eq!(decoded, samples);
+}
+
+#[test]
+fn frames_add_up_to_the_sample_rate() {
+    for fps in [1, 7, 24, 30, 50, 60, 144, 1000] {
+        let mut synth = Synth::new(SAMPLE_RATE, fps);
+        let lengths: Vec<usize> = (0..fps * 10).map(|_| synth.frame().len()).collect();
+        for second in lengths.chunks(fps as usize) {
+            assert_eq!(
+                second.iter().sum::<usize>(),
+                SAMPLE_RATE as usize,
+                "{fps} FPS"
+            );
+        }
+        let shortest = SAMPLE_RATE as usize / fps as usize;
+        assert!(
+            lengths
+                .iter()
+                .all(|&len| len == shortest || len == shortest + 1),
+            "{fps} FPS"
+        );
+    }
+}
+
+/// Beeps on every frame whose input has Left held.
+struct Beeper;
+
+impl Game for Beeper {
+    const NAME: &'static str = "Beeper";
+    const FPS: usize = 24;
+    const WIDTH: usize = 1;
+    const HEIGHT: usize = 1;
+
+    fn new(_args: Vec<String>) -> u64 {
+        0
+    }
+
+    fn step(_state: u64, input: Input) -> u64 {
+        u64::from(input.is_pressed(Button::Left))
+    }
+
+    fn render(_state: u64) -> Vec<DrawCommand> {
+        Vec::new()
+    }
+
+    fn sounds(_previous: u64, next: u64) -> Vec<Sound> {
+        if next == 0 {
+            return Vec::new();
+        }
+        vec![Sound {
+            waveform: Waveform::Square,
+            pitch: 24,
+            duration: Duration::from_millis(20),
+            volume: 1.0,
+        }]
+    }
+}
+
+#[test]
+fn replays_render_one_frame_of_samples_per_input() {
+    let mut replay = Replay::new(0);
+    for frame in 0..100 {
+        let input = if frame == 50 {
+            Input::from(Button::Left)
+        } else {
+            Input::NONE
+        };
+        replay.push(input, Beeper::step(0, input));
+    }
+    let samples = output::render_replay::<Beeper>(&replay);
+    assert_eq!(samples.len(), 100 * SAMPLE_RATE as usize / Beeper::FPS);
+
+    // Silence apart from the beep on frame 50
+    let frame = |i: usize| {
+        let bounds = |i: usize| i * SAMPLE_RATE as usize / Beeper::FPS;
+        &samples[bounds(i)..bounds(i + 1)]
+    };
+    assert!(frame(49).iter().all(|&sample| sample == 0));
+    assert!(frame(50).iter().any(|&sample| sample != 0));
+    assert!(frame(51).iter().all(|&sample| sample == 0));
+    assert_eq!(output::render_replay::<Beeper>(&replay), samples);
+}
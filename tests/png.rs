@@ -0,0 +1,59 @@
+use bitwise_games::png;
+
+#[test]
+fn round_trips() {
+    // Big enough to need more than one stored deflate block
+    let (width, height) = (200u32, 120);
+    let pixels: Vec<u32> = (0..width * height)
+        .map(|i| i.wrapping_mul(0x9e37_79b9) & 0xff_ff_ff)
+        .collect();
+    let encoded = png::encode(&pixels, width, height);
+    assert_eq!(png::decode(&encoded), Some((pixels, width, height)));
+
+    assert_eq!(
+        png::decode(&png::encode(&[], 0, 0)),
+        Some((Vec::new(), 0, 0))
+    );
+}
+
+#[test]
+fn rejects_corruption() {
+    let encoded = png::encode(&[0x12_34_56; 12], 4, 3);
+    for i in 0..encoded.len() {
+        let mut corrupt = encoded.clone();
+        corrupt[i] ^= 0x01;
+        assert_eq!(png::decode(&corrupt), None, "byte {i}");
+    }
+    for len in 0..encoded.len() {
+        assert_eq!(png::decode(&encoded[..len]), None, "{len} bytes");
+    }
+}
+
+/// The PNG chunk checksum, bit by bit.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[test]
+fn rejects_sizes_whose_pixel_count_overflows() {
+    for (width, height) in [(u32::MAX, u32::MAX), (u32::MAX, 2), (1 << 31, 1 << 31)] {
+        let mut encoded = png::encode(&[0], 1, 1);
+        // The IHDR chunk follows the 8 byte signature and its own length
+        encoded[16..20].copy_from_slice(&width.to_be_bytes());
+        encoded[20..24].copy_from_slice(&height.to_be_bytes());
+        let crc = crc32(&encoded[12..29]);
+        encoded[29..33].copy_from_slice(&crc.to_be_bytes());
+        assert_eq!(png::decode(&encoded), None, "{width}x{height}");
+    }
+}
@@ -0,0 +1,88 @@
+use bitwise_games::replay::{MAX_FRAMES, Replay, ReplayError};
+use bitwise_games::{Button, Input};
+
+fn replay() -> Replay {
+    let mut replay = Replay::new(0x1234_5678_9abc_def0);
+    let inputs = [
+        Input::NONE,
+        Input::NONE,
+        Input::from(Button::Left),
+        Input::from_bits(0xff),
+        Input::NONE,
+    ];
+    for (frame, &input) in (1..).zip(inputs.iter().cycle().take(500)) {
+        replay.push(input, frame);
+    }
+    replay
+}
+
+#[test]
+fn round_trips() {
+    let replay = replay();
+    assert_eq!(Replay::decode(&replay.encode()).unwrap(), replay);
+
+    let empty = Replay::new(u64::MAX);
+    assert_eq!(Replay::decode(&empty.encode()).unwrap(), empty);
+}
+
+#[test]
+fn runs_are_compressed() {
+    let mut replay = Replay::new(0);
+    for frame in 0..1000 {
+        replay.push(Input::NONE, frame);
+    }
+    // Header, then a single run
+    assert_eq!(replay.encode().len(), 21 + 3);
+}
+
+#[test]
+fn rejects_every_truncation() {
+    let bytes = replay().encode();
+    // Cuts that land between two runs still decode, just with fewer inputs
+    for len in 0..bytes.len() {
+        match Replay::decode(&bytes[..len]) {
+            Ok(decoded) => {
+                assert!(len >= 21, "length {len}");
+                assert!(decoded.inputs.len() < 500, "length {len}");
+            }
+            Err(ReplayError::Truncated) => {}
+            Err(err) => panic!("length {len}: {err}"),
+        }
+    }
+    assert!(matches!(
+        Replay::decode(&bytes[..20]),
+        Err(ReplayError::Truncated)
+    ));
+}
+
+#[test]
+fn rejects_bad_headers() {
+    let mut bytes = replay().encode();
+    bytes[4] = 2;
+    assert!(matches!(
+        Replay::decode(&bytes),
+        Err(ReplayError::UnsupportedVersion(2))
+    ));
+    bytes[0] = b'X';
+    assert!(matches!(Replay::decode(&bytes), Err(ReplayError::BadMagic)));
+}
+
+#[test]
+fn rejects_huge_runs() {
+    // A single run of 2^64 - 1 frames
+    let mut bytes = Replay::new(0).encode();
+    bytes.extend_from_slice(&[0xff; 9]);
+    bytes.extend_from_slice(&[0x01, 0x00]);
+    assert!(matches!(Replay::decode(&bytes), Err(ReplayError::TooLong)));
+
+    // Runs that only go over the limit together: MAX_FRAMES, then one more
+    let mut longest = Replay::new(0);
+    longest.inputs = vec![Input::NONE; MAX_FRAMES as usize];
+    let mut bytes = longest.encode();
+    assert_eq!(
+        Replay::decode(&bytes).unwrap().inputs.len() as u64,
+        MAX_FRAMES
+    );
+    bytes.extend_from_slice(&[0x01, 0x00]);
+    assert!(matches!(Replay::decode(&bytes), Err(ReplayError::TooLong)));
+}
@@ -0,0 +1,97 @@
+use bitwise_games::draw_command::DrawCommand;
+use bitwise_games::replay::Replay;
+use bitwise_games::rewind::RewindBuffer;
+use bitwise_games::{Button, Game, Input, Session};
+
+/// Mixes every input into the state, so each frame's state is distinct.
+struct Mixer;
+
+impl Game for Mixer {
+    const NAME: &'static str = "Mixer";
+    const FPS: usize = 60;
+    const WIDTH: usize = 1;
+    const HEIGHT: usize = 1;
+
+    fn new(_args: Vec<String>) -> u64 {
+        1
+    }
+
+    fn step(state: u64, input: Input) -> u64 {
+        state.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ u64::from(input.bits())
+    }
+
+    fn render(_state: u64) -> Vec<DrawCommand> {
+        Vec::new()
+    }
+}
+
+fn input(frame: u64) -> Input {
+    if frame.is_multiple_of(3) {
+        Input::from(Button::Left)
+    } else {
+        Input::NONE
+    }
+}
+
+#[test]
+fn full_buffers_drop_the_oldest_states() {
+    let mut history = RewindBuffer::new(3);
+    for state in 1..=5 {
+        history.push(state);
+    }
+    assert_eq!((history.len(), history.capacity()), (3, 3));
+    assert_eq!(history.last(), Some(5));
+    assert_eq!(history.pop(), Some(5));
+    assert_eq!(history.pop(), Some(4));
+    assert_eq!(history.pop(), Some(3));
+    assert_eq!(history.pop(), None);
+    assert!(history.is_empty());
+
+    let mut disabled = RewindBuffer::new(0);
+    disabled.push(1);
+    assert_eq!(disabled.pop(), None);
+}
+
+#[test]
+fn stepping_back_stops_at_the_oldest_state() {
+    let mut session = Session::<Mixer>::new(Vec::new());
+    let mut history = RewindBuffer::new(4);
+    let mut states = vec![session.state()];
+    for frame in 0..10 {
+        session.step_recording(input(frame), &mut history);
+        states.push(session.state());
+    }
+
+    for frame in (6..10).rev() {
+        assert!(session.step_back(&mut history));
+        assert_eq!(session.frame(), frame);
+        assert_eq!(session.state(), states[frame as usize]);
+    }
+    assert!(!session.step_back(&mut history));
+    assert_eq!(session.frame(), 6);
+    assert_eq!(session.state(), states[6]);
+}
+
+#[test]
+fn recordings_stay_aligned_with_the_frame_count() {
+    let mut session = Session::<Mixer>::new(Vec::new());
+    let mut history = RewindBuffer::new(5);
+    let mut recording = Replay::new(session.state());
+
+    // Play, rewind further than the history reaches, then play on, as the
+    // window runner does
+    for round in 0..3 {
+        for frame in 0..8 {
+            let input = input(frame + round);
+            session.step_recording(input, &mut history);
+            recording.push(input, session.state());
+        }
+        while session.step_back(&mut history) {
+            recording.pop(session.state());
+        }
+        assert_eq!(recording.inputs.len() as u64, session.frame());
+        assert_eq!(recording.final_state, session.state());
+        recording.verify::<Mixer>().unwrap();
+    }
+    assert_eq!(session.frame(), 3 * 8 - 3 * 5);
+}
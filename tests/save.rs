@@ -0,0 +1,47 @@
+use bitwise_games::save::SaveSlots;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// A directory of its own for each test, which doesn't exist yet.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("bitwise-games-save-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn slots_round_trip() {
+    let dir = temp_dir("round-trip");
+    let slots = SaveSlots::new(&dir, "Bitwise Breakout");
+    for (slot, state) in [(0, 0), (1, u64::MAX), (9, 0x0123_4567_89ab_cdef)] {
+        slots.save(slot, state).unwrap();
+        assert_eq!(slots.load(slot).unwrap(), state);
+    }
+    slots.save(1, 42).unwrap();
+    assert_eq!(slots.load(1).unwrap(), 42);
+    assert_eq!(slots.path(1), dir.join("bitwise-breakout-1.sav"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_slots_are_not_found() {
+    let dir = temp_dir("missing");
+    let slots = SaveSlots::new(&dir, "Breakout");
+    assert_eq!(slots.load(3).unwrap_err().kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn slots_of_the_wrong_size_are_invalid() {
+    let dir = temp_dir("size");
+    let slots = SaveSlots::new(&dir, "Breakout");
+    slots.save(0, 7).unwrap();
+    for len in [0, 7, 9, 64] {
+        fs::write(slots.path(0), vec![0xa5; len]).unwrap();
+        let err = slots.load(0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{len} bytes");
+        assert_eq!(err.to_string(), "save slot 0 is not 8 bytes");
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,87 @@
+use bitwise_games::scale::{Viewport, upscale};
+
+#[test]
+fn viewports_use_the_largest_whole_scale() {
+    assert_eq!(
+        Viewport::fit(64, 32, 64, 32),
+        Viewport {
+            x: 0,
+            y: 0,
+            scale: 1
+        }
+    );
+    assert_eq!(
+        Viewport::fit(64, 32, 256, 128),
+        Viewport {
+            x: 0,
+            y: 0,
+            scale: 4
+        }
+    );
+    // 3.5x horizontally and 5x vertically, so 3x with bars on all sides
+    assert_eq!(
+        Viewport::fit(64, 32, 224, 160),
+        Viewport {
+            x: 16,
+            y: 32,
+            scale: 3
+        }
+    );
+    // An odd leftover puts the extra pixel after the frame
+    assert_eq!(
+        Viewport::fit(4, 4, 13, 9),
+        Viewport {
+            x: 2,
+            y: 0,
+            scale: 2
+        }
+    );
+}
+
+#[test]
+fn small_targets_crop_at_scale_one() {
+    assert_eq!(
+        Viewport::fit(64, 32, 32, 64),
+        Viewport {
+            x: 0,
+            y: 16,
+            scale: 1
+        }
+    );
+    assert_eq!(
+        Viewport::fit(64, 32, 0, 0),
+        Viewport {
+            x: 0,
+            y: 0,
+            scale: 1
+        }
+    );
+}
+
+#[test]
+fn upscaling_repeats_pixels_inside_black_bars() {
+    let mut target = vec![9; 5 * 6];
+    upscale(&[1, 2, 3, 4], 2, 2, &mut target, 5, 6);
+    #[rustfmt::skip]
+    assert_eq!(
+        target,
+        [
+            0, 0, 0, 0, 0,
+            1, 1, 2, 2, 0,
+            1, 1, 2, 2, 0,
+            3, 3, 4, 4, 0,
+            3, 3, 4, 4, 0,
+            0, 0, 0, 0, 0,
+        ]
+    );
+}
+
+#[test]
+fn upscaling_into_a_smaller_target_crops() {
+    let mut target = vec![9; 2 * 3];
+    upscale(&[1, 2, 3, 4, 5, 6], 3, 2, &mut target, 2, 3);
+    assert_eq!(target, [1, 2, 4, 5, 0, 0]);
+
+    let mut empty: Vec<u32> = Vec::new();
+    upscale(&[1, 2, 3, 4], 2, 2, &mut empty, 0, 0);
+}
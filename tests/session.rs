@@ -0,0 +1,56 @@
+use bitwise_games::draw_command::{Color, DrawCommand, Rectangle, Style};
+use bitwise_games::{Button, Game, Input, render_frame, run_headless, run_headless_frames};
+
+/// A pixel that moves right while Right is held.
+struct Walker;
+
+impl Game for Walker {
+    const NAME: &'static str = "Walker";
+    const FPS: usize = 60;
+    const WIDTH: usize = 4;
+    const HEIGHT: usize = 1;
+
+    fn new(args: Vec<String>) -> u64 {
+        args.first().map_or(0, |arg| arg.parse().unwrap())
+    }
+
+    fn step(state: u64, input: Input) -> u64 {
+        (state + u64::from(input.is_pressed(Button::Right))) % 4
+    }
+
+    fn render(state: u64) -> Vec<DrawCommand> {
+        vec![DrawCommand::Rectangle(Rectangle {
+            x: state as i32,
+            y: 0,
+            width: 1,
+            height: 1,
+            color: Color::from_rgb(255, 255, 255),
+            style: Style::Fill,
+        })]
+    }
+}
+
+#[test]
+fn headless_runs_return_each_state() {
+    let right = Input::from(Button::Right);
+    let inputs = [right, Input::NONE, right, right, right];
+    assert_eq!(
+        run_headless::<Walker>(vec!["1".to_string()], &inputs),
+        [2, 2, 3, 0, 1]
+    );
+}
+
+#[test]
+fn headless_runs_can_rasterize_each_state() {
+    let right = Input::from(Button::Right);
+    let frames = run_headless_frames::<Walker>(Vec::new(), &[right, right, Input::NONE]);
+    let states: Vec<u64> = frames.iter().map(|(state, _)| *state).collect();
+    assert_eq!(states, [1, 2, 2]);
+    for (state, fb) in &frames {
+        let mut expected = vec![0; 4];
+        expected[*state as usize] = 0xffffff;
+        assert_eq!((fb.width, fb.height), (4, 1));
+        assert_eq!(fb.pixels, expected);
+        assert_eq!(fb.pixels, render_frame::<Walker>(*state).pixels);
+    }
+}
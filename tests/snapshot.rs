@@ -0,0 +1,137 @@
+use bitwise_games::draw_command::{Color, DrawCommand, Rectangle, Style};
+use bitwise_games::frame_buffer::FrameBuffer;
+use bitwise_games::png;
+use bitwise_games::snapshot::{self, Snapshot};
+use bitwise_games::{Game, Input};
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+const WHITE: Color = Color::from_rgb(255, 255, 255);
+
+/// A white pixel that moves one step right per frame.
+struct Dot;
+
+impl Game for Dot {
+    const NAME: &'static str = "Dot";
+    const FPS: usize = 60;
+    const WIDTH: usize = 4;
+    const HEIGHT: usize = 2;
+
+    fn new(_args: Vec<String>) -> u64 {
+        0
+    }
+
+    fn step(state: u64, _input: Input) -> u64 {
+        (state + 1) % Self::WIDTH as u64
+    }
+
+    fn render(state: u64) -> Vec<DrawCommand> {
+        vec![DrawCommand::Rectangle(Rectangle {
+            x: state as i32,
+            y: 0,
+            width: 1,
+            height: 1,
+            color: WHITE,
+            style: Style::Fill,
+        })]
+    }
+}
+
+/// An empty directory of its own for each test.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "bitwise-games-snapshot-{}-{name}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_reference(dir: &Path, pixels: Vec<u32>, width: u32, height: u32) {
+    FrameBuffer::from_pixels(pixels, width, height)
+        .write_png(dir.join("dot.png"))
+        .unwrap();
+}
+
+fn read_png(path: PathBuf) -> (Vec<u32>, u32, u32) {
+    png::decode(&fs::read(path).unwrap()).unwrap()
+}
+
+/// The panic message of a failing `assert`.
+fn failure(snapshot: &Snapshot<Dot>) -> String {
+    let err = panic::catch_unwind(AssertUnwindSafe(|| snapshot.assert())).unwrap_err();
+    err.downcast_ref::<String>().unwrap().clone()
+}
+
+#[test]
+fn mismatches_write_actual_and_diff_images() {
+    if snapshot::updating() {
+        return;
+    }
+    let dir = temp_dir("mismatch");
+    write_reference(&dir, vec![0; 8], 4, 2);
+
+    let message = failure(Snapshot::<Dot>::new(&dir, "dot", 0).capture());
+    assert!(message.contains("1 pixels differ"), "{message}");
+
+    let (actual, width, height) = read_png(dir.join("dot.actual.png"));
+    assert_eq!((width, height), (4, 2));
+    assert_eq!(actual[0], 0xff_ff_ff);
+    assert!(actual[1..].iter().all(|&pixel| pixel == 0));
+
+    let (diff, width, height) = read_png(dir.join("dot.diff.png"));
+    assert_eq!((width, height), (4, 2));
+    assert_eq!(diff[0], 0xff_00_4d);
+    assert!(diff[1..].iter().all(|&pixel| pixel == 0x40_40_40));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn size_mismatches_write_only_the_actual_image() {
+    if snapshot::updating() {
+        return;
+    }
+    let dir = temp_dir("size");
+    write_reference(&dir, vec![0; 4], 4, 1);
+
+    let message = failure(Snapshot::<Dot>::new(&dir, "dot", 0).capture().capture());
+    assert!(message.contains("snapshot is 8x2 but"), "{message}");
+    assert!(message.contains("is 4x1"), "{message}");
+    assert_eq!(read_png(dir.join("dot.actual.png")).1, 8);
+    assert!(!dir.join("dot.diff.png").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn passing_removes_stale_failure_images() {
+    if snapshot::updating() {
+        return;
+    }
+    let dir = temp_dir("stale");
+    let mut pixels = vec![0; 8];
+    pixels[1] = 0xff_ff_ff;
+    write_reference(&dir, pixels, 4, 2);
+    fs::write(dir.join("dot.actual.png"), "stale").unwrap();
+    fs::write(dir.join("dot.diff.png"), "stale").unwrap();
+
+    Snapshot::<Dot>::new(&dir, "dot", 0)
+        .step([Input::NONE])
+        .capture()
+        .assert();
+    assert!(!dir.join("dot.actual.png").exists());
+    assert!(!dir.join("dot.diff.png").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_references_fail() {
+    if snapshot::updating() {
+        return;
+    }
+    let dir = temp_dir("missing");
+    let message = failure(Snapshot::<Dot>::new(&dir, "dot", 0).capture());
+    assert!(message.contains("no reference image"), "{message}");
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,212 @@
+use bitwise_games::draw_command::{
+    BlendMode, Circle, Clip, Color, DrawCommand, Line, Polygon, Rectangle, Sprite, Style, Text,
+    Triangle,
+};
+use bitwise_games::svg;
+
+const RED: Color = Color::from_rgb(255, 0, 77);
+const HALF_RED: Color = Color::from_rgba(255, 0, 77, 128);
+
+/// The elements written for `commands`, without the document and background.
+fn elements(commands: &[DrawCommand]) -> Vec<String> {
+    let svg = svg::to_svg(commands, 16, 12);
+    let mut lines: Vec<String> = svg.lines().map(str::to_string).collect();
+    assert_eq!(
+        lines[..2],
+        [
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="12" viewBox="0 0 16 12" shape-rendering="crispEdges">"#,
+            r##"<rect width="16" height="12" fill="#000000"/>"##,
+        ]
+    );
+    assert_eq!(lines.pop().as_deref(), Some("</svg>"));
+    lines.split_off(2)
+}
+
+fn rect(x: i32, y: i32, width: u32, height: u32, color: Color, style: Style) -> DrawCommand {
+    DrawCommand::Rectangle(Rectangle {
+        x,
+        y,
+        width,
+        height,
+        color,
+        style,
+    })
+}
+
+#[test]
+fn writes_rectangles() {
+    assert_eq!(
+        elements(&[
+            rect(1, 2, 3, 4, RED, Style::Fill),
+            rect(1, 2, 3, 4, RED, Style::Stroke),
+        ]),
+        [
+            r##"<rect x="1" y="2" width="3" height="4" fill="#ff004d"/>"##,
+            r##"<rect x="1.5" y="2.5" width="2" height="3" fill="none" stroke="#ff004d" stroke-width="1"/>"##,
+        ]
+    );
+}
+
+#[test]
+fn writes_thin_outlines_as_filled_rectangles() {
+    // A zero-sized SVG rect would draw nothing, but the rasterizer draws a line
+    assert_eq!(
+        elements(&[
+            rect(1, 2, 1, 4, RED, Style::Stroke),
+            rect(1, 2, 5, 1, RED, Style::Stroke),
+        ]),
+        [
+            r##"<rect x="1" y="2" width="1" height="4" fill="#ff004d"/>"##,
+            r##"<rect x="1" y="2" width="5" height="1" fill="#ff004d"/>"##,
+        ]
+    );
+}
+
+#[test]
+fn writes_lines_and_circles() {
+    assert_eq!(
+        elements(&[
+            DrawCommand::Line(Line {
+                x1: 0,
+                y1: 0,
+                x2: 5,
+                y2: 3,
+                color: HALF_RED,
+            }),
+            DrawCommand::Circle(Circle {
+                x: 8,
+                y: 8,
+                radius: 3,
+                color: RED,
+                style: Style::Fill,
+            }),
+            DrawCommand::Circle(Circle {
+                x: 8,
+                y: 8,
+                radius: 3,
+                color: RED,
+                style: Style::Stroke,
+            }),
+        ]),
+        [
+            r##"<line x1="0.5" y1="0.5" x2="5.5" y2="3.5" stroke-linecap="square" fill="none" stroke="#ff004d" stroke-width="1" opacity="0.502"/>"##,
+            r##"<circle cx="8.5" cy="8.5" r="3.5" fill="#ff004d"/>"##,
+            r##"<circle cx="8.5" cy="8.5" r="3" fill="none" stroke="#ff004d" stroke-width="1"/>"##,
+        ]
+    );
+}
+
+#[test]
+fn writes_triangles_and_polygons() {
+    assert_eq!(
+        elements(&[
+            DrawCommand::Triangle(Triangle {
+                x1: 0,
+                y1: 0,
+                x2: 4,
+                y2: 0,
+                x3: 0,
+                y3: 4,
+                color: RED,
+                style: Style::Fill,
+            }),
+            DrawCommand::Polygon(Polygon {
+                points: vec![(0, 0), (4, 1), (2, 5)],
+                color: HALF_RED,
+                style: Style::Stroke,
+            }),
+        ]),
+        [
+            r##"<polygon points="0.5,0.5 4.5,0.5 0.5,4.5" fill="#ff004d" fill-rule="evenodd" stroke="#ff004d" stroke-width="1" stroke-linejoin="round"/>"##,
+            r##"<polygon points="0.5,0.5 4.5,1.5 2.5,5.5" fill="none" stroke="#ff004d" stroke-width="1" stroke-linejoin="round" opacity="0.502"/>"##,
+        ]
+    );
+}
+
+#[test]
+fn writes_text_and_sprites_pixel_by_pixel() {
+    assert_eq!(
+        elements(&[
+            DrawCommand::Text(Text {
+                x: 1,
+                y: 1,
+                text: "1".to_string(),
+                scale: 1,
+                color: RED,
+            }),
+            DrawCommand::Sprite(Sprite {
+                scale: 2,
+                ..Sprite::mask(0, 0, 0b1000_0001, RED)
+            }),
+        ]),
+        [
+            r##"<path d="M2 1h1v1h-1zM1 2h1v1h-1zM2 2h1v1h-1zM2 3h1v1h-1zM2 4h1v1h-1zM1 5h1v1h-1zM2 5h1v1h-1zM3 5h1v1h-1z" fill="#ff004d"/>"##,
+            r##"<path d="M0 0h2v2h-2zM14 0h2v2h-2z" fill="#ff004d"/>"##,
+        ]
+    );
+}
+
+#[test]
+fn leaves_out_sprite_pixels_outside_the_frame() {
+    // Columns 0 and 1 land at x = -4 and 0, columns 6 and 7 at 20 and 24
+    assert_eq!(
+        elements(&[DrawCommand::Sprite(Sprite {
+            scale: 4,
+            ..Sprite::mask(-4, 0, 0b1100_0011, RED)
+        })]),
+        [r##"<path d="M0 0h4v4h-4z" fill="#ff004d"/>"##]
+    );
+}
+
+#[test]
+fn nests_clips_and_blend_modes() {
+    let square = |color| rect(0, 0, 2, 2, color, Style::Fill);
+    assert_eq!(
+        elements(&[
+            DrawCommand::BlendMode(BlendMode::Add),
+            DrawCommand::Clip(Some(Clip {
+                x: 1,
+                y: 1,
+                width: 4,
+                height: 4,
+            })),
+            square(RED),
+            DrawCommand::Clip(Some(Clip {
+                x: 2,
+                y: 2,
+                width: 4,
+                height: 4,
+            })),
+            DrawCommand::BlendMode(BlendMode::Multiply),
+            square(HALF_RED),
+            DrawCommand::Clip(None),
+            DrawCommand::BlendMode(BlendMode::Alpha),
+            square(RED),
+        ]),
+        [
+            r#"<clipPath id="clip1"><rect x="1" y="1" width="4" height="4"/></clipPath>"#,
+            r#"<g clip-path="url(#clip1)">"#,
+            r##"<rect x="0" y="0" width="2" height="2" fill="#ff004d" style="mix-blend-mode:plus-lighter"/>"##,
+            "</g>",
+            r#"<clipPath id="clip2"><rect x="2" y="2" width="4" height="4"/></clipPath>"#,
+            r#"<g clip-path="url(#clip2)">"#,
+            r##"<rect x="0" y="0" width="2" height="2" fill="#ff004d" opacity="0.502" style="mix-blend-mode:multiply"/>"##,
+            "</g>",
+            r##"<rect x="0" y="0" width="2" height="2" fill="#ff004d"/>"##,
+        ]
+    );
+}
+
+#[test]
+fn closes_a_clip_left_open() {
+    let lines = elements(&[
+        DrawCommand::Clip(Some(Clip {
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+        })),
+        rect(0, 0, 2, 2, RED, Style::Fill),
+    ]);
+    assert_eq!(lines.last().map(String::as_str), Some("</g>"));
+}